@@ -64,6 +64,15 @@ impl<'a> IgnoreMatch<'a> {
     fn hidden() -> IgnoreMatch<'static> {
         IgnoreMatch(IgnoreMatchInner::Hidden)
     }
+
+    // CHANGED: Expose the gitignore glob that produced a match, so callers
+    // can explain why a path was ignored or whitelisted.
+    pub(crate) fn glob(&self) -> Option<&'a gitignore::Glob> {
+        match self.0 {
+            IgnoreMatchInner::Gitignore(glob) => Some(glob),
+            _ => None,
+        }
+    }
 }
 
 /// Options for the ignore matcher, shared between the matcher itself and the
@@ -5,7 +5,8 @@ use std::{
 };
 
 use crate::{
-    Error, Match, PartialErrorBuilder, dir::Ignore, pathutil::is_hidden_path,
+    Error, Match, PartialErrorBuilder, dir::Ignore, gitignore,
+    pathutil::is_hidden_path,
 };
 
 /// A cached matcher for checking paths against hierarchical ignore files.
@@ -99,7 +100,9 @@ enum CachedDir {
     /// The directory may not be descended into because it was ignored by a
     /// path rule or hidden-file filtering. Every descendant is consequently
     /// ignored, and ignore files inside this directory are not loaded.
-    Ignored,
+    ///
+    /// CHANGED: The gitignore glob that ignored the directory is kept, if any.
+    Ignored(Option<gitignore::Glob>),
 }
 
 impl IncrementalIgnore {
@@ -339,8 +342,8 @@ impl IncrementalIgnore {
                 Some(CachedDir::Allowed(ignore)) => {
                     return Some(self.match_path(ignore, relative, is_dir));
                 }
-                Some(CachedDir::Ignored) => {
-                    return Some(IncrementalMatch::ignore());
+                Some(CachedDir::Ignored(glob)) => {
+                    return Some(IncrementalMatch::ignore_by(glob.clone()));
                 }
                 None => {}
             }
@@ -367,8 +370,8 @@ impl IncrementalIgnore {
                     ignore = cached.clone();
                     continue;
                 }
-                Some(CachedDir::Ignored) => {
-                    return Some(IncrementalMatch::ignore());
+                Some(CachedDir::Ignored(glob)) => {
+                    return Some(IncrementalMatch::ignore_by(glob.clone()));
                 }
                 None => {}
             }
@@ -378,8 +381,10 @@ impl IncrementalIgnore {
             let is_hidden =
                 self.options.hidden && mat.is_none() && is_hidden_path(&path);
             if mat.is_ignore() || is_hidden {
-                self.dirs.insert(dir.clone(), CachedDir::Ignored);
-                return Some(IncrementalMatch::ignore());
+                let glob = mat.inner().and_then(|m| m.glob()).cloned();
+                self.dirs
+                    .insert(dir.clone(), CachedDir::Ignored(glob.clone()));
+                return Some(IncrementalMatch::ignore_by(glob));
             }
             let (child, err) = ignore.add_child(&path);
             errs.maybe_push(err);
@@ -397,10 +402,10 @@ impl IncrementalIgnore {
         is_dir: bool,
     ) -> IncrementalMatch {
         let path = self.root.join(relative);
-        let mut mat = IncrementalMatch::from_match(
-            ignore.matched(&path, is_dir).map(|_| ()),
-            is_dir,
-        );
+        let mat = ignore.matched(&path, is_dir);
+        let glob = mat.inner().and_then(|m| m.glob()).cloned();
+        let mut mat = IncrementalMatch::from_match(mat.map(|_| ()), is_dir)
+            .with_glob(glob);
         // Whether a file is hidden or not has low precedence in filtering. We
         // only check it if we haven't matched anything above. This permits
         // callers to whitelist hidden files or directories.
@@ -450,6 +455,8 @@ pub struct IncrementalMatch {
     mat: Match<()>,
     should_descend: bool,
     is_within_depth: bool,
+    /// CHANGED: The gitignore glob responsible for this match, if any.
+    glob: Option<gitignore::Glob>,
 }
 
 impl IncrementalMatch {
@@ -458,6 +465,7 @@ impl IncrementalMatch {
             mat: Match::None,
             should_descend: is_dir,
             is_within_depth: true,
+            glob: None,
         }
     }
 
@@ -466,12 +474,26 @@ impl IncrementalMatch {
             mat: Match::Ignore(()),
             should_descend: false,
             is_within_depth: true,
+            glob: None,
         }
     }
 
+    fn ignore_by(glob: Option<gitignore::Glob>) -> IncrementalMatch {
+        IncrementalMatch::ignore().with_glob(glob)
+    }
+
+    fn with_glob(self, glob: Option<gitignore::Glob>) -> IncrementalMatch {
+        IncrementalMatch { glob, ..self }
+    }
+
     fn from_match(mat: Match<()>, is_dir: bool) -> IncrementalMatch {
         let should_descend = is_dir && !mat.is_ignore();
-        IncrementalMatch { mat, should_descend, is_within_depth: true }
+        IncrementalMatch {
+            mat,
+            should_descend,
+            is_within_depth: true,
+            glob: None,
+        }
     }
 
     fn no_descent(self) -> IncrementalMatch {
@@ -527,6 +549,15 @@ impl IncrementalMatch {
         self.is_within_depth
     }
 
+    /// Returns the gitignore glob responsible for this match, if any.
+    ///
+    /// For a path inside an ignored directory, this is the glob that ignored
+    /// the directory. This is `None` for non-matches, and for matches that
+    /// were not caused by a gitignore glob, e.g., hidden-file filtering.
+    pub fn glob(&self) -> Option<&gitignore::Glob> {
+        self.glob.as_ref()
+    }
+
     /// Inverts the match so that `Ignore` becomes `Whitelist` and
     /// `Whitelist` becomes `Ignore`. A non-match remains the same.
     pub fn invert(self) -> IncrementalMatch {
@@ -854,7 +885,7 @@ mod tests {
         assert!(matchedf(&mut m, "blocked/first").is_ignore());
         assert!(matches!(
             m.dirs.get(Path::new("blocked")),
-            Some(CachedDir::Ignored)
+            Some(CachedDir::Ignored(_))
         ));
         assert!(matchedf(&mut m, "blocked/second").is_ignore());
     }
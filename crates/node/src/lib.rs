@@ -1,3 +1,4 @@
+use std::path::Path;
 use utf16::IndexConverter;
 
 #[macro_use]
@@ -16,6 +17,12 @@ pub struct ChangedContent {
 
   /// File extension
   pub extension: String,
+
+  /// Whether the file (or folder) at `file` was removed
+  pub removed: Option<bool>,
+
+  /// Previous path of the file, when the file was renamed to `file`
+  pub renamed_from: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,6 +51,18 @@ pub struct SourceEntry {
 impl From<ChangedContent> for tailwindcss_oxide::ChangedContent {
   fn from(changed_content: ChangedContent) -> Self {
     if let Some(file) = changed_content.file {
+      if changed_content.removed == Some(true) {
+        return tailwindcss_oxide::ChangedContent::Removed(file.into());
+      }
+
+      if let Some(from) = changed_content.renamed_from {
+        return tailwindcss_oxide::ChangedContent::Renamed(
+          from.into(),
+          file.into(),
+          changed_content.extension,
+        );
+      }
+
       return tailwindcss_oxide::ChangedContent::File(file.into(), changed_content.extension);
     }
 
@@ -90,6 +109,172 @@ impl From<SourceEntry> for tailwindcss_oxide::PublicSourceEntry {
 pub struct ScannerOptions {
   /// Glob sources
   pub sources: Option<Vec<SourceEntry>>,
+
+  /// Directory to store the persistent scan cache in, e.g.: `.tailwindcss/cache`
+  pub cache_dir: Option<String>,
+
+  /// How incremental scans decide whether a file changed, defaults to `mtime`
+  pub change_detection: Option<ChangeDetection>,
+
+  /// Changes to the built-in auto source detection rules
+  pub auto_source_detection: Option<AutoSourceDetectionOptions>,
+
+  /// Route extensions or globs to a built-in pre-processor
+  pub pre_processors: Option<Vec<PreProcessorMapping>>,
+
+  /// Files larger than this many bytes are not scanned
+  pub max_file_size: Option<u32>,
+
+  /// Skip files that look minified, e.g.: a `vendor.min.js` bundle
+  pub skip_minified_files: Option<bool>,
+
+  /// Only extract candidates that start with this prefix, e.g.: `tw` for `tw:flex`
+  pub prefix: Option<String>,
+
+  /// Utility roots and variant names of the design system, candidates that can never match them
+  /// are not extracted
+  pub allowlist: Option<CandidateAllowlist>,
+
+  /// Track how often every candidate occurs, see `usageReport`
+  pub usage_statistics: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CandidateAllowlist {
+  /// Utility roots, e.g.: `bg` for `bg-red-500` or `flex`
+  pub utilities: Vec<String>,
+
+  /// Variant names, e.g.: `hover` or `group` for `group-hover`
+  pub variants: Vec<String>,
+}
+
+impl From<CandidateAllowlist> for tailwindcss_oxide::CandidateAllowlist {
+  fn from(allowlist: CandidateAllowlist) -> Self {
+    Self::new(allowlist.utilities, allowlist.variants)
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+#[napi(object)]
+pub struct RuleListChanges {
+  /// Entries to add to the list
+  pub add: Option<Vec<String>>,
+
+  /// Entries to remove from the list
+  pub remove: Option<Vec<String>>,
+}
+
+impl From<RuleListChanges> for tailwindcss_oxide::RuleListChanges {
+  fn from(changes: RuleListChanges) -> Self {
+    Self {
+      add: changes.add.unwrap_or_default(),
+      remove: changes.remove.unwrap_or_default(),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct AutoSourceDetectionOptions {
+  /// Folders that are never scanned, e.g.: `node_modules`
+  pub ignored_content_dirs: Option<RuleListChanges>,
+
+  /// File extensions that are never scanned, without the leading `.`, e.g.: `css`
+  pub ignored_extensions: Option<RuleListChanges>,
+
+  /// Binary file extensions, without the leading `.`, e.g.: `png`
+  pub binary_extensions: Option<RuleListChanges>,
+
+  /// Files that are never scanned, e.g.: `package-lock.json`
+  pub ignored_files: Option<RuleListChanges>,
+}
+
+impl From<AutoSourceDetectionOptions> for tailwindcss_oxide::AutoSourceDetectionOptions {
+  fn from(options: AutoSourceDetectionOptions) -> Self {
+    Self {
+      ignored_content_dirs: options.ignored_content_dirs.unwrap_or_default().into(),
+      ignored_extensions: options.ignored_extensions.unwrap_or_default().into(),
+      binary_extensions: options.binary_extensions.unwrap_or_default().into(),
+      ignored_files: options.ignored_files.unwrap_or_default().into(),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct PreProcessorMapping {
+  /// A file extension without the leading `.`, e.g.: `blade.php`, or a glob, e.g.: `*.liquid.html`
+  pub pattern: String,
+
+  pub pre_processor: BuiltinPreProcessor,
+}
+
+impl From<PreProcessorMapping> for tailwindcss_oxide::PreProcessorMapping {
+  fn from(mapping: PreProcessorMapping) -> Self {
+    Self {
+      pattern: mapping.pattern,
+      pre_processor: mapping.pre_processor.into(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[napi(string_enum = "kebab-case")]
+pub enum BuiltinPreProcessor {
+  None,
+  Clojure,
+  Elixir,
+  Haml,
+  Json,
+  Markdown,
+  Pug,
+  Razor,
+  Ruby,
+  Rust,
+  Slim,
+  Svelte,
+  TemplateToolkit,
+  Twig,
+  Vue,
+}
+
+impl From<BuiltinPreProcessor> for tailwindcss_oxide::BuiltinPreProcessor {
+  fn from(pre_processor: BuiltinPreProcessor) -> Self {
+    match pre_processor {
+      BuiltinPreProcessor::None => Self::None,
+      BuiltinPreProcessor::Clojure => Self::Clojure,
+      BuiltinPreProcessor::Elixir => Self::Elixir,
+      BuiltinPreProcessor::Haml => Self::Haml,
+      BuiltinPreProcessor::Json => Self::Json,
+      BuiltinPreProcessor::Markdown => Self::Markdown,
+      BuiltinPreProcessor::Pug => Self::Pug,
+      BuiltinPreProcessor::Razor => Self::Razor,
+      BuiltinPreProcessor::Ruby => Self::Ruby,
+      BuiltinPreProcessor::Rust => Self::Rust,
+      BuiltinPreProcessor::Slim => Self::Slim,
+      BuiltinPreProcessor::Svelte => Self::Svelte,
+      BuiltinPreProcessor::TemplateToolkit => Self::TemplateToolkit,
+      BuiltinPreProcessor::Twig => Self::Twig,
+      BuiltinPreProcessor::Vue => Self::Vue,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[napi(string_enum = "kebab-case")]
+pub enum ChangeDetection {
+  Mtime,
+  ContentHash,
+}
+
+impl From<ChangeDetection> for tailwindcss_oxide::ChangeDetection {
+  fn from(change_detection: ChangeDetection) -> Self {
+    match change_detection {
+      ChangeDetection::Mtime => Self::Mtime,
+      ChangeDetection::ContentHash => Self::ContentHash,
+    }
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -106,6 +291,328 @@ pub struct CandidateWithPosition {
 
   /// The position of the candidate inside the content file
   pub position: i64,
+
+  /// Whether this is a candidate or a CSS variable
+  pub kind: ExtractedKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[napi(string_enum = "kebab-case")]
+pub enum ExtractedKind {
+  Candidate,
+  CssVariable,
+}
+
+impl From<tailwindcss_oxide::ExtractedKind> for ExtractedKind {
+  fn from(kind: tailwindcss_oxide::ExtractedKind) -> Self {
+    match kind {
+      tailwindcss_oxide::ExtractedKind::Candidate => Self::Candidate,
+      tailwindcss_oxide::ExtractedKind::CssVariable => Self::CssVariable,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CandidatePosition {
+  /// File the candidate was found in
+  pub file: String,
+
+  /// The candidate string, or the CSS variable
+  pub candidate: String,
+
+  /// Whether this is a candidate or a CSS variable
+  pub kind: ExtractedKind,
+
+  /// UTF-16 offset of the start of the candidate inside the file
+  pub start: i64,
+
+  /// UTF-16 offset of the end of the candidate inside the file
+  pub end: i64,
+
+  /// 1-based line number
+  pub line: i64,
+
+  /// 0-based UTF-16 offset from the start of the line
+  pub column: i64,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CandidateLocation {
+  /// File the candidate was found in
+  pub file: String,
+
+  /// Byte offsets of every occurrence of the candidate inside the file
+  pub offsets: Vec<i64>,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CandidateLocations {
+  /// The candidate string
+  pub candidate: String,
+
+  /// All files the candidate was found in
+  pub locations: Vec<CandidateLocation>,
+}
+
+impl From<tailwindcss_oxide::CandidateLocation> for CandidateLocation {
+  fn from(location: tailwindcss_oxide::CandidateLocation) -> Self {
+    Self {
+      file: location.file.to_string_lossy().to_string(),
+      offsets: location
+        .offsets
+        .into_iter()
+        .map(|offset| offset as i64)
+        .collect(),
+    }
+  }
+}
+
+/// Files are reported by their canonical path, with symlinks resolved.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ScanDiff {
+  /// Candidates that weren't known before
+  pub added: Vec<String>,
+
+  /// Candidates that are no longer used by any file
+  pub removed: Vec<String>,
+
+  /// Files that were (re-)scanned because they are new or have changed
+  pub changed_files: Vec<String>,
+
+  /// Files that were scanned before, but no longer exist or are no longer part of the sources
+  pub deleted_files: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ScanStats {
+  /// Files that were read during the scan, but skipped because they look binary
+  pub binary_files: Vec<String>,
+
+  /// Wall clock time of the whole scan, in milliseconds
+  pub duration_ms: f64,
+
+  /// Walking the file system to find the files to scan
+  pub walking: PhaseStats,
+
+  /// Reading files from disk
+  pub reading: PhaseStats,
+
+  /// Pre-processing files that have a pre-processor
+  pub pre_processing: PhaseStats,
+
+  /// Extracting candidates, including raw content
+  pub extraction: PhaseStats,
+}
+
+/// Files are read, pre-processed and extracted on multiple threads, so the durations of these
+/// phases are summed over all threads.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct PhaseStats {
+  /// In milliseconds
+  pub duration_ms: f64,
+  pub files: u32,
+  pub bytes: i64,
+}
+
+impl From<tailwindcss_oxide::PhaseStats> for PhaseStats {
+  fn from(stats: tailwindcss_oxide::PhaseStats) -> Self {
+    Self {
+      duration_ms: stats.duration.as_secs_f64() * 1000.0,
+      files: stats.files as u32,
+      bytes: stats.bytes as i64,
+    }
+  }
+}
+
+#[napi(string_enum = "kebab-case")]
+pub enum Verdict {
+  Scanned,
+  Ignored,
+  NotMatchingSource,
+  Skipped,
+  OutsideSources,
+  NotFound,
+}
+
+impl From<tailwindcss_oxide::Verdict> for Verdict {
+  fn from(verdict: tailwindcss_oxide::Verdict) -> Self {
+    match verdict {
+      tailwindcss_oxide::Verdict::Scanned => Self::Scanned,
+      tailwindcss_oxide::Verdict::Ignored => Self::Ignored,
+      tailwindcss_oxide::Verdict::NotMatchingSource => Self::NotMatchingSource,
+      tailwindcss_oxide::Verdict::Skipped => Self::Skipped,
+      tailwindcss_oxide::Verdict::OutsideSources => Self::OutsideSources,
+      tailwindcss_oxide::Verdict::NotFound => Self::NotFound,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct MatchedRule {
+  /// Where the rule was defined: `ignore-file`, `source`, `ignored-content-dir`,
+  /// `ignored-extension`, `ignored-file` or `binary-extension`
+  pub origin: String,
+
+  /// The ignore file the rule was defined in
+  pub file: Option<String>,
+
+  /// The 1-based line number of the rule inside the ignore file
+  pub line: Option<u32>,
+
+  /// The rule as it was written
+  pub pattern: String,
+
+  /// Whether the rule allows the path instead of ignoring it
+  pub whitelist: bool,
+}
+
+impl From<tailwindcss_oxide::MatchedRule> for MatchedRule {
+  fn from(rule: tailwindcss_oxide::MatchedRule) -> Self {
+    use tailwindcss_oxide::RuleOrigin;
+
+    let (origin, file, line) = match rule.origin {
+      RuleOrigin::IgnoreFile { file, line } => (
+        "ignore-file",
+        Some(file.to_string_lossy().to_string()),
+        line.map(|line| line as u32),
+      ),
+      RuleOrigin::Source => ("source", None, None),
+      RuleOrigin::IgnoredContentDir => ("ignored-content-dir", None, None),
+      RuleOrigin::IgnoredExtension => ("ignored-extension", None, None),
+      RuleOrigin::IgnoredFile => ("ignored-file", None, None),
+      RuleOrigin::BinaryExtension => ("binary-extension", None, None),
+    };
+
+    Self {
+      origin: origin.to_string(),
+      file,
+      line,
+      pattern: rule.pattern,
+      whitelist: rule.whitelist,
+    }
+  }
+}
+
+#[napi(object)]
+pub struct Explanation {
+  /// The resolved path
+  pub path: String,
+
+  /// Whether the path is scanned, and if not, why
+  pub verdict: Verdict,
+
+  /// The rule that decided the verdict, if any
+  pub rule: Option<MatchedRule>,
+
+  /// Why the file is skipped because of its size or contents, e.g.: `looks like a binary file`
+  pub skip_reason: Option<String>,
+
+  /// All sources that include the path
+  pub sources: Vec<GlobEntry>,
+}
+
+impl From<tailwindcss_oxide::Explanation> for Explanation {
+  fn from(explanation: tailwindcss_oxide::Explanation) -> Self {
+    Self {
+      path: explanation.path.to_string_lossy().to_string(),
+      verdict: explanation.verdict.into(),
+      rule: explanation.rule.map(Into::into),
+      skip_reason: explanation.skip_reason.map(|reason| reason.to_string()),
+      sources: explanation.sources.into_iter().map(Into::into).collect(),
+    }
+  }
+}
+
+impl From<tailwindcss_oxide::ScanDiff> for ScanDiff {
+  fn from(diff: tailwindcss_oxide::ScanDiff) -> Self {
+    Self {
+      added: diff.added,
+      removed: diff.removed,
+      changed_files: diff.changed_files,
+      deleted_files: diff.deleted_files,
+    }
+  }
+}
+
+impl From<&tailwindcss_oxide::ScanStats> for ScanStats {
+  fn from(stats: &tailwindcss_oxide::ScanStats) -> Self {
+    Self {
+      binary_files: stats.binary_files.clone(),
+      duration_ms: stats.duration.as_secs_f64() * 1000.0,
+      walking: stats.walking.into(),
+      reading: stats.reading.into(),
+      pre_processing: stats.pre_processing.into(),
+      extraction: stats.extraction.into(),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CandidateUsage {
+  pub candidate: String,
+
+  /// How often the candidate occurs in all files and content
+  pub occurrences: u32,
+
+  /// The number of files the candidate occurs in
+  pub files: u32,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ExtensionUsage {
+  /// The extension without the leading `.`, e.g.: `html`
+  pub extension: String,
+
+  /// The number of files with this extension
+  pub files: u32,
+
+  /// The number of unique candidates per file, summed over all files with this extension
+  pub candidates: u32,
+
+  /// How often candidates occur in all files with this extension
+  pub occurrences: u32,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct UsageReport {
+  pub candidates: Vec<CandidateUsage>,
+  pub extensions: Vec<ExtensionUsage>,
+}
+
+impl From<tailwindcss_oxide::UsageReport> for UsageReport {
+  fn from(report: tailwindcss_oxide::UsageReport) -> Self {
+    Self {
+      candidates: report
+        .candidates
+        .into_iter()
+        .map(|usage| CandidateUsage {
+          candidate: usage.candidate,
+          occurrences: usage.occurrences as u32,
+          files: usage.files as u32,
+        })
+        .collect(),
+      extensions: report
+        .extensions
+        .into_iter()
+        .map(|usage| ExtensionUsage {
+          extension: usage.extension,
+          files: usage.files as u32,
+          candidates: usage.candidates as u32,
+          occurrences: usage.occurrences as u32,
+        })
+        .collect(),
+    }
+  }
 }
 
 #[napi]
@@ -113,10 +620,31 @@ impl Scanner {
   #[napi(constructor)]
   pub fn new(opts: ScannerOptions) -> Self {
     Self {
-      scanner: tailwindcss_oxide::Scanner::new(match opts.sources {
-        Some(sources) => sources.into_iter().map(Into::into).collect(),
-        None => vec![],
-      }),
+      scanner: tailwindcss_oxide::Scanner::new_with_options(
+        match opts.sources {
+          Some(sources) => sources.into_iter().map(Into::into).collect(),
+          None => vec![],
+        },
+        tailwindcss_oxide::ScannerOptions {
+          cache_dir: opts.cache_dir.map(Into::into),
+          change_detection: opts.change_detection.map(Into::into).unwrap_or_default(),
+          auto_source_detection: opts
+            .auto_source_detection
+            .map(Into::into)
+            .unwrap_or_default(),
+          pre_processors: opts
+            .pre_processors
+            .unwrap_or_default()
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+          max_file_size: opts.max_file_size.map(u64::from),
+          skip_minified_files: opts.skip_minified_files.unwrap_or_default(),
+          prefix: opts.prefix,
+          allowlist: opts.allowlist.map(Into::into),
+          usage_statistics: opts.usage_statistics.unwrap_or_default(),
+        },
+      ),
     }
   }
 
@@ -132,31 +660,78 @@ impl Scanner {
       .scan_content(input.into_iter().map(Into::into).collect())
   }
 
+  #[napi]
+  pub fn scan_diff(&mut self) -> ScanDiff {
+    self.scanner.scan_diff().into()
+  }
+
+  #[napi]
+  pub fn scan_files_diff(&mut self, input: Vec<ChangedContent>) -> ScanDiff {
+    self
+      .scanner
+      .scan_content_diff(input.into_iter().map(Into::into).collect())
+      .into()
+  }
+
   #[napi]
   pub fn get_candidates_with_positions(
     &mut self,
     input: ChangedContent,
   ) -> Vec<CandidateWithPosition> {
-    let content = input.content.unwrap_or_else(|| {
-      std::fs::read_to_string(input.file.unwrap()).expect("Failed to read file")
-    });
-
-    let input = ChangedContent {
-      file: None,
-      content: Some(content.clone()),
-      extension: input.extension,
-    };
+    // A file that can't be read has no candidates. Positions point to the bytes of the file, which
+    // are not necessarily UTF-8, convert them through the content the candidates were extracted
+    // from.
+    let (content, positions) = self
+      .scanner
+      .get_candidates_with_positions_and_content(input.into());
+    let mut utf16_idx = IndexConverter::from_bytes(&content);
 
-    let mut utf16_idx = IndexConverter::new(&content[..]);
+    positions
+      .into_iter()
+      .map(|(candidate, position, kind)| CandidateWithPosition {
+        candidate,
+        position: utf16_idx.get(content.decoded_offset(position)),
+        kind: kind.into(),
+      })
+      .collect()
+  }
 
+  /// Every occurrence of every candidate in all files of the sources
+  #[napi]
+  pub fn get_all_candidates_with_positions(&mut self) -> Vec<CandidatePosition> {
     self
       .scanner
-      .get_candidates_with_positions(input.into())
-      .into_iter()
-      .map(|(candidate, position)| CandidateWithPosition {
-        candidate,
-        position: utf16_idx.get(position),
+      .map_all_candidates_with_positions(|content, positions| {
+        // Positions point to the bytes of the file, which are not necessarily UTF-8. Convert them
+        // through the decoded content the scanner extracted the candidates from.
+        let converter = IndexConverter::from_bytes(content);
+        let mut line_idx = converter.clone();
+        let mut start_idx = converter.clone();
+        let mut end_idx = converter;
+
+        let utf16 =
+          |converter: &mut IndexConverter, pos: usize| converter.get(content.decoded_offset(pos));
+
+        positions
+          .into_iter()
+          .map(|position| {
+            let line_start = utf16(&mut line_idx, position.range.start - position.column);
+            let start = utf16(&mut start_idx, position.range.start);
+
+            CandidatePosition {
+              file: position.file.to_string_lossy().to_string(),
+              candidate: position.candidate,
+              kind: position.kind.into(),
+              start,
+              end: utf16(&mut end_idx, position.range.end),
+              line: position.line as i64,
+              column: start - line_start,
+            }
+          })
+          .collect::<Vec<_>>()
       })
+      .into_iter()
+      .flatten()
       .collect()
   }
 
@@ -170,6 +745,39 @@ impl Scanner {
     self.scanner.get_scanned_files()
   }
 
+  #[napi(getter)]
+  pub fn removed_candidates(&self) -> Vec<String> {
+    self.scanner.get_removed_candidates()
+  }
+
+  #[napi(getter)]
+  pub fn scan_stats(&self) -> ScanStats {
+    self.scanner.get_scan_stats().into()
+  }
+
+  #[napi(getter)]
+  pub fn usage_report(&self) -> Option<UsageReport> {
+    self.scanner.get_usage_report().map(Into::into)
+  }
+
+  #[napi]
+  pub fn explain(&mut self, path: String) -> Explanation {
+    self.scanner.explain(Path::new(&path)).into()
+  }
+
+  #[napi(getter)]
+  pub fn candidate_locations(&self) -> Vec<CandidateLocations> {
+    self
+      .scanner
+      .get_candidate_locations()
+      .into_iter()
+      .map(|(candidate, locations)| CandidateLocations {
+        candidate,
+        locations: locations.into_iter().map(Into::into).collect(),
+      })
+      .collect()
+  }
+
   #[napi(getter)]
   pub fn globs(&mut self) -> Vec<GlobEntry> {
     self
@@ -190,3 +798,100 @@ impl Scanner {
       .collect()
   }
 }
+
+#[derive(Debug, Clone, Copy)]
+#[napi(string_enum = "kebab-case")]
+pub enum UtilityKind {
+  Named,
+  ArbitraryProperty,
+}
+
+impl From<tailwindcss_oxide::UtilityKind> for UtilityKind {
+  fn from(kind: tailwindcss_oxide::UtilityKind) -> Self {
+    match kind {
+      tailwindcss_oxide::UtilityKind::Named => Self::Named,
+      tailwindcss_oxide::UtilityKind::ArbitraryProperty => Self::ArbitraryProperty,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[napi(string_enum = "kebab-case")]
+pub enum ValueKind {
+  Named,
+  Arbitrary,
+  ArbitraryVariable,
+}
+
+impl From<tailwindcss_oxide::ValueKind> for ValueKind {
+  fn from(kind: tailwindcss_oxide::ValueKind) -> Self {
+    match kind {
+      tailwindcss_oxide::ValueKind::Named => Self::Named,
+      tailwindcss_oxide::ValueKind::Arbitrary => Self::Arbitrary,
+      tailwindcss_oxide::ValueKind::ArbitraryVariable => Self::ArbitraryVariable,
+    }
+  }
+}
+
+/// A range inside the candidate, in UTF-16 code units
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CandidateSpan {
+  pub start: i64,
+  pub end: i64,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CandidateValue {
+  pub kind: ValueKind,
+
+  /// The contents of the value, without the surrounding `[…]` or `(…)`
+  pub span: CandidateSpan,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ParsedCandidate {
+  /// Every variant without the trailing `:`
+  pub variants: Vec<CandidateSpan>,
+
+  pub kind: UtilityKind,
+
+  /// The root of a named utility, or the property of an arbitrary property
+  pub root: CandidateSpan,
+
+  pub value: Option<CandidateValue>,
+  pub modifier: Option<CandidateValue>,
+  pub important: bool,
+  pub negative: bool,
+}
+
+/// Split a candidate into its variants, root, value and modifier. Returns `null` when the input is
+/// not a valid candidate.
+#[napi]
+pub fn parse_candidate(candidate: String) -> Option<ParsedCandidate> {
+  let parsed = tailwindcss_oxide::Candidate::parse(candidate.as_bytes())?;
+
+  let mut utf16_idx = IndexConverter::new(&candidate);
+  let mut span = |range: std::ops::Range<usize>| CandidateSpan {
+    start: utf16_idx.get(range.start),
+    end: utf16_idx.get(range.end),
+  };
+
+  Some(ParsedCandidate {
+    variants: parsed.variants.into_iter().map(&mut span).collect(),
+    kind: parsed.kind.into(),
+    root: span(parsed.root),
+    value: parsed.value.map(|value| CandidateValue {
+      kind: value.kind.into(),
+      span: span(value.span),
+    }),
+    modifier: parsed.modifier.map(|modifier| CandidateValue {
+      kind: modifier.kind.into(),
+      span: span(modifier.span),
+    }),
+    important: parsed.important,
+    negative: parsed.negative,
+  })
+}
@@ -2,13 +2,20 @@
 /// *character* indexes
 #[derive(Clone)]
 pub struct IndexConverter<'a> {
-  input: &'a str,
+  input: &'a [u8],
   curr_utf8: usize,
   curr_utf16: usize,
 }
 
 impl<'a> IndexConverter<'a> {
   pub fn new(input: &'a str) -> Self {
+    Self::from_bytes(input.as_bytes())
+  }
+
+  /// Like `new`, but for content that is not guaranteed to be valid UTF-8. Every invalid sequence
+  /// counts as a single replacement character, just like when the content is decoded in
+  /// JavaScript.
+  pub fn from_bytes(input: &'a [u8]) -> Self {
     Self {
       input,
       curr_utf8: 0,
@@ -27,19 +34,28 @@ impl<'a> IndexConverter<'a> {
       self.curr_utf16 = 0;
     }
 
-    // SAFETY: No matter what `pos` is passed into this function `curr_utf8`
-    // will only ever be incremented up to the length of the input string.
-    //
-    // This eliminates a "potential" panic that cannot actually happen
-    let slice = unsafe { self.input.get_unchecked(self.curr_utf8..) };
+    // `curr_utf8` always points to the start of a character, or of an invalid sequence
+    'chunks: for chunk in self.input[self.curr_utf8..].utf8_chunks() {
+      for c in chunk.valid().chars() {
+        if self.curr_utf8 >= pos {
+          break 'chunks;
+        }
+
+        self.curr_utf8 += c.len_utf8();
+        self.curr_utf16 += c.len_utf16();
+      }
+
+      if chunk.invalid().is_empty() {
+        continue;
+      }
 
-    for c in slice.chars() {
       if self.curr_utf8 >= pos {
         break;
       }
 
-      self.curr_utf8 += c.len_utf8();
-      self.curr_utf16 += c.len_utf16();
+      // Decoded as a single `U+FFFD`
+      self.curr_utf8 += chunk.invalid().len();
+      self.curr_utf16 += 1;
     }
 
     self.curr_utf16 as i64
@@ -91,4 +107,25 @@ mod test {
       assert_eq!(converter.get(idx_utf8), idx_utf16);
     }
   }
+
+  #[test]
+  fn test_index_converter_with_invalid_utf8() {
+    // `\xE9` is `é` in Latin-1, and an invalid sequence in UTF-8
+    let mut converter = IndexConverter::from_bytes(b"caf\xE9 \xF0\x9F\x94\xA5 flex");
+
+    let map = HashMap::from([
+      (3, 3),
+      // The invalid byte is a single replacement character
+      (4, 4),
+      (5, 5),
+      // The 🔥 is still two utf-16 characters
+      (9, 7),
+      (10, 8),
+      (14, 12),
+    ]);
+
+    for (idx_utf8, idx_utf16) in map {
+      assert_eq!(converter.get(idx_utf8), idx_utf16);
+    }
+  }
 }
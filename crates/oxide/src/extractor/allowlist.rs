@@ -0,0 +1,123 @@
+use crate::extractor::candidate::{Candidate, UtilityKind};
+use fxhash::FxHashSet;
+
+/// The utility roots and variant names of a design system, used to drop candidates that can
+/// never match, e.g.: `class` or `return`.
+///
+/// Every entry also allows everything that starts with the entry followed by a `-`. E.g.: `bg`
+/// allows `bg-red-500` and `bg-[#0088cc]`, and `group` allows `group-hover` and `group-[.open]`.
+/// Arbitrary properties and arbitrary variants are always allowed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CandidateAllowlist {
+    utilities: FxHashSet<String>,
+    variants: FxHashSet<String>,
+}
+
+impl CandidateAllowlist {
+    pub fn new(utilities: Vec<String>, variants: Vec<String>) -> Self {
+        Self {
+            utilities: utilities.into_iter().collect(),
+            variants: variants.into_iter().collect(),
+        }
+    }
+
+    /// Describes the allowlist, independent of the order of the entries.
+    pub fn cache_key(&self) -> String {
+        let mut utilities = self.utilities.iter().collect::<Vec<_>>();
+        utilities.sort_unstable();
+        let mut variants = self.variants.iter().collect::<Vec<_>>();
+        variants.sort_unstable();
+
+        format!("{utilities:?} {variants:?}")
+    }
+
+    /// Whether the candidate can match. `skip_variants` is the number of leading variants that are
+    /// not checked, e.g.: `1` to skip the prefix in `tw:flex`.
+    pub fn allows(&self, input: &[u8], skip_variants: usize) -> bool {
+        // Only drop candidates we understand
+        let Some(candidate) = Candidate::parse(input) else {
+            return true;
+        };
+
+        let utility_allowed = match candidate.kind {
+            UtilityKind::Named => matches(&self.utilities, &input[candidate.root]),
+            UtilityKind::ArbitraryProperty => true,
+        };
+
+        utility_allowed
+            && candidate
+                .variants
+                .into_iter()
+                .skip(skip_variants)
+                .all(|variant| {
+                    let variant = &input[variant];
+                    variant.starts_with(b"[") || matches(&self.variants, variant)
+                })
+    }
+}
+
+/// Whether the name, or any part of it that is followed by a `-`, is in the set. Arbitrary values
+/// and modifiers are ignored.
+fn matches(set: &FxHashSet<String>, name: &[u8]) -> bool {
+    let end = name
+        .iter()
+        .position(|c| matches!(c, b'[' | b'(' | b'/'))
+        .unwrap_or(name.len());
+
+    let Ok(name) = std::str::from_utf8(&name[..end]) else {
+        return true;
+    };
+
+    set.contains(name)
+        || name
+            .match_indices('-')
+            .any(|(idx, _)| set.contains(&name[..idx]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CandidateAllowlist;
+
+    #[test]
+    fn test_allowlist() {
+        let allowlist = CandidateAllowlist::new(
+            ["flex", "bg", "mt", "translate-x", "@container"]
+                .map(String::from)
+                .to_vec(),
+            ["hover", "group", "data", "md"].map(String::from).to_vec(),
+        );
+
+        for candidate in [
+            "flex",
+            "flex-1",
+            "bg-red-500/50",
+            "bg-[#0088cc]",
+            "-mt-4",
+            "-translate-x-[calc(100%-1rem)]",
+            "@container/sidebar",
+            "[color:red]",
+            "hover:flex",
+            "group-hover/sidebar:bg-red-500",
+            "data-[state=open]:flex",
+            "md:[&.active]:[color:red]",
+        ] {
+            assert!(allowlist.allows(candidate.as_bytes(), 0), "{candidate}");
+        }
+
+        for candidate in [
+            "class",
+            "div",
+            "return",
+            "flexbox",
+            "focus:flex",
+            "hover:underline",
+            "translate-y-4",
+        ] {
+            assert!(!allowlist.allows(candidate.as_bytes(), 0), "{candidate}");
+        }
+
+        // The prefix is not a variant of the design system
+        assert!(allowlist.allows(b"tw:hover:flex", 1));
+        assert!(!allowlist.allows(b"tw:hover:flex", 0));
+    }
+}
@@ -0,0 +1,333 @@
+use crate::cursor;
+use crate::extractor::arbitrary_property_machine::ArbitraryPropertyMachine;
+use crate::extractor::machine::{Machine, MachineState};
+use crate::extractor::modifier_machine::ModifierMachine;
+use crate::extractor::named_utility_machine::NamedUtilityMachine;
+use crate::extractor::utility_machine::UtilityMachine;
+use crate::extractor::variant_machine::VariantMachine;
+use std::ops::Range;
+
+/// A candidate split into its parts, e.g.: `hover:-bg-[#0088cc]/50!`.
+///
+/// All spans are byte ranges in the parsed input. Values are spans of their contents, without the
+/// surrounding `[…]` or `(…)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// Every variant without the trailing `:`, e.g.: `hover` and `[&.active]`
+    pub variants: Vec<Range<usize>>,
+
+    pub kind: UtilityKind,
+
+    /// The root of the utility without the `-` of negative utilities, e.g.: `bg` for
+    /// `bg-[#0088cc]`, or the property of arbitrary properties, e.g.: `color` for `[color:red]`.
+    ///
+    /// Which part of a named value belongs to the root depends on the design system, `bg-red-500`
+    /// uses `bg-red-500` as the root and has no value. See `root_splits` for all options.
+    pub root: Range<usize>,
+
+    pub value: Option<CandidateValue>,
+
+    /// The modifier without the leading `/`, e.g.: `50` for `bg-red-500/50`
+    pub modifier: Option<CandidateValue>,
+
+    /// Whether the utility is marked as important, e.g.: `flex!` or the legacy `!flex`
+    pub important: bool,
+
+    /// Whether the utility is negative, e.g.: `-mt-4`
+    pub negative: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UtilityKind {
+    /// E.g.: `flex`, `bg-red-500` or `bg-[#0088cc]`
+    Named,
+
+    /// E.g.: `[color:red]`
+    ArbitraryProperty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateValue {
+    pub kind: ValueKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// E.g.: `50` in `bg-red-500/50`
+    Named,
+
+    /// E.g.: `#0088cc` in `bg-[#0088cc]`
+    Arbitrary,
+
+    /// E.g.: `--my-color` in `bg-(--my-color)`
+    ArbitraryVariable,
+}
+
+impl Candidate {
+    /// Parse a single candidate. Returns `None` when the input, as a whole, is not a valid
+    /// candidate.
+    pub fn parse(input: &[u8]) -> Option<Self> {
+        let len = input.len();
+        let mut cursor = cursor::Cursor::new(input);
+
+        // Variants, every variant is followed by a `:`
+        let mut variants = vec![];
+        let mut variant_machine = VariantMachine::default();
+        loop {
+            let mut variant_cursor = cursor;
+            match variant_machine.next(&mut variant_cursor) {
+                MachineState::Done(span) => {
+                    variants.push(span.start..span.end);
+                    cursor.move_to(span.end + 1);
+                }
+                MachineState::Idle => break,
+            }
+        }
+
+        // The utility has to span the rest of the input
+        let start = cursor.pos;
+        let mut utility_cursor = cursor;
+        match UtilityMachine::default().next(&mut utility_cursor) {
+            MachineState::Done(span) if span.start == start && span.end + 1 == len => {}
+            _ => return None,
+        }
+
+        let legacy_important = input[start] == b'!';
+        let important = legacy_important || input[len - 1] == b'!';
+        let end = if important && !legacy_important {
+            len - 1
+        } else {
+            len
+        };
+        cursor.move_to(if legacy_important { start + 1 } else { start });
+
+        let (kind, root, value, negative) = if cursor.curr() == b'[' {
+            let mut machine: ArbitraryPropertyMachine = Default::default();
+            let MachineState::Done(span) = machine.next(&mut cursor) else {
+                return None;
+            };
+
+            let colon = input[span.start..span.end]
+                .iter()
+                .position(|c| *c == b':')?;
+            let root = span.start + 1..span.start + colon;
+            let value = CandidateValue {
+                kind: ValueKind::Arbitrary,
+                span: span.start + colon + 1..span.end,
+            };
+
+            (UtilityKind::ArbitraryProperty, root, Some(value), false)
+        } else {
+            let name_start = cursor.pos;
+            let mut machine: NamedUtilityMachine = Default::default();
+            let MachineState::Done(span) = machine.next(&mut cursor) else {
+                return None;
+            };
+
+            let negative = input[name_start] == b'-';
+            let root_start = if negative { name_start + 1 } else { name_start };
+            let (root, value) = split_named(input, root_start..span.end + 1);
+
+            (UtilityKind::Named, root, value, negative)
+        };
+
+        // Modifier
+        let modifier_start = cursor.pos + 1;
+        let modifier = if modifier_start < end {
+            cursor.move_to(modifier_start);
+            let MachineState::Done(span) = ModifierMachine::default().next(&mut cursor) else {
+                return None;
+            };
+
+            Some(value_of(input, span.start + 1..span.end + 1))
+        } else {
+            None
+        };
+
+        Some(Self {
+            variants,
+            kind,
+            root,
+            value,
+            modifier,
+            important,
+            negative,
+        })
+    }
+
+    /// Every way a named utility can be split into a root and a named value, longest root first,
+    /// e.g.: `bg-red-500`, `bg-red` + `500` and `bg` + `red-500`.
+    pub fn root_splits(&self, input: &[u8]) -> Vec<(Range<usize>, Option<CandidateValue>)> {
+        if self.kind != UtilityKind::Named || self.value.is_some() {
+            return vec![(self.root.clone(), self.value.clone())];
+        }
+
+        let root = &input[self.root.clone()];
+        std::iter::once((self.root.clone(), None))
+            .chain(
+                (1..root.len())
+                    .rev()
+                    .filter(|idx| root[*idx] == b'-')
+                    .map(|idx| {
+                        let split = self.root.start + idx;
+                        let value = CandidateValue {
+                            kind: ValueKind::Named,
+                            span: split + 1..self.root.end,
+                        };
+
+                        (self.root.start..split, Some(value))
+                    }),
+            )
+            .collect()
+    }
+}
+
+/// Split a named utility into its root and its arbitrary value, e.g.: `bg` and `#0088cc` for
+/// `bg-[#0088cc]`.
+fn split_named(input: &[u8], name: Range<usize>) -> (Range<usize>, Option<CandidateValue>) {
+    let bytes = &input[name.clone()];
+    let open = match bytes.last() {
+        Some(b']') => b'[',
+        Some(b')') => b'(',
+        _ => return (name, None),
+    };
+
+    // The root never contains brackets, so the first `-[` or `-(` starts the value
+    match bytes.windows(2).position(|pair| pair == [b'-', open]) {
+        Some(idx) => (
+            name.start..name.start + idx,
+            Some(value_of(input, name.start + idx + 1..name.end)),
+        ),
+        None => (name, None),
+    }
+}
+
+fn value_of(input: &[u8], span: Range<usize>) -> CandidateValue {
+    match input.get(span.start) {
+        Some(b'[') => CandidateValue {
+            kind: ValueKind::Arbitrary,
+            span: span.start + 1..span.end - 1,
+        },
+        Some(b'(') => CandidateValue {
+            kind: ValueKind::ArbitraryVariable,
+            span: span.start + 1..span.end - 1,
+        },
+        _ => CandidateValue {
+            kind: ValueKind::Named,
+            span,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Candidate, UtilityKind, ValueKind};
+    use pretty_assertions::assert_eq;
+
+    /// A readable version of the parsed candidate
+    fn parse(input: &str) -> Option<String> {
+        let candidate = Candidate::parse(input.as_bytes())?;
+        let slice = |span: &std::ops::Range<usize>| &input[span.clone()];
+
+        let mut parts = vec![];
+        for variant in &candidate.variants {
+            parts.push(format!("variant({})", slice(variant)));
+        }
+        parts.push(match candidate.kind {
+            UtilityKind::Named => format!("root({})", slice(&candidate.root)),
+            UtilityKind::ArbitraryProperty => format!("property({})", slice(&candidate.root)),
+        });
+        for (name, value) in [
+            ("value", &candidate.value),
+            ("modifier", &candidate.modifier),
+        ] {
+            if let Some(value) = value {
+                let kind = match value.kind {
+                    ValueKind::Named => "named",
+                    ValueKind::Arbitrary => "arbitrary",
+                    ValueKind::ArbitraryVariable => "variable",
+                };
+                parts.push(format!("{name}({kind} {})", slice(&value.span)));
+            }
+        }
+        if candidate.important {
+            parts.push("important".into());
+        }
+        if candidate.negative {
+            parts.push("negative".into());
+        }
+
+        Some(parts.join(" "))
+    }
+
+    #[test]
+    fn test_parse_candidates() {
+        for (input, expected) in [
+            ("flex", "root(flex)"),
+            ("bg-red-500", "root(bg-red-500)"),
+            ("-mt-4", "root(mt-4) negative"),
+            ("bg-red-500/50", "root(bg-red-500) modifier(named 50)"),
+            (
+                "hover:focus:bg-[#0088cc]/[50%]!",
+                "variant(hover) variant(focus) root(bg) value(arbitrary #0088cc) modifier(arbitrary 50%) important",
+            ),
+            (
+                "bg-(--my-color)/(--my-opacity)",
+                "root(bg) value(variable --my-color) modifier(variable --my-opacity)",
+            ),
+            ("!underline", "root(underline) important"),
+            (
+                "[&.active]:[color:red]",
+                "variant([&.active]) property(color) value(arbitrary red)",
+            ),
+            (
+                "tw:data-[state=open]:-translate-x-[calc(100%-1rem)]",
+                "variant(tw) variant(data-[state=open]) root(translate-x) value(arbitrary calc(100%-1rem)) negative",
+            ),
+            (
+                "[--my-color:theme(colors.red.500)]/50",
+                "property(--my-color) value(arbitrary theme(colors.red.500)) modifier(named 50)",
+            ),
+            ("@container/sidebar", "root(@container) modifier(named sidebar)"),
+        ] {
+            assert_eq!(parse(input).as_deref(), Some(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_candidates() {
+        for input in [
+            "",
+            "hover:",
+            "flex ",
+            "bg-red-500//50",
+            "!flex!",
+            "Flex",
+            "[color:red",
+        ] {
+            assert_eq!(parse(input), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_root_splits() {
+        let input = "-bg-red-500";
+        let candidate = Candidate::parse(input.as_bytes()).unwrap();
+
+        let splits = candidate
+            .root_splits(input.as_bytes())
+            .into_iter()
+            .map(|(root, value)| (&input[root], value.map(|value| &input[value.span])))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            splits,
+            vec![
+                ("bg-red-500", None),
+                ("bg-red", Some("500")),
+                ("bg", Some("red-500")),
+            ]
+        );
+    }
+}
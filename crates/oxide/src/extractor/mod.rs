@@ -1,15 +1,18 @@
 use crate::cursor;
+use crate::extractor::allowlist::CandidateAllowlist;
 use crate::extractor::machine::Span;
 use candidate_machine::CandidateMachine;
 use css_variable_machine::CssVariableMachine;
 use machine::{Machine, MachineState};
 use std::fmt;
 
+pub mod allowlist;
 pub mod arbitrary_property_machine;
 pub mod arbitrary_value_machine;
 pub mod arbitrary_variable_machine;
 mod boundary;
 pub mod bracket_stack;
+pub mod candidate;
 pub mod candidate_machine;
 pub mod css_variable_machine;
 pub mod machine;
@@ -53,9 +56,42 @@ impl fmt::Display for Extracted<'_> {
     }
 }
 
+impl Extracted<'_> {
+    pub fn kind(&self) -> ExtractedKind {
+        match self {
+            Extracted::Candidate(_) => ExtractedKind::Candidate,
+            Extracted::CssVariable(_) => ExtractedKind::CssVariable,
+        }
+    }
+}
+
+/// What kind of value was extracted, without the value itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtractedKind {
+    Candidate,
+    CssVariable,
+}
+
+/// Narrows down which candidates are extracted. CSS variables are always extracted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractorOptions {
+    /// Only extract candidates that use this prefix as their first variant, e.g.: `tw` to extract
+    /// `tw:flex` but not `flex`
+    pub prefix: Option<String>,
+
+    /// Only extract candidates whose utility and variants are known to the design system
+    pub allowlist: Option<CandidateAllowlist>,
+}
+
+static DEFAULT_OPTIONS: ExtractorOptions = ExtractorOptions {
+    prefix: None,
+    allowlist: None,
+};
+
 #[derive(Debug)]
 pub struct Extractor<'a> {
     cursor: cursor::Cursor<'a>,
+    options: &'a ExtractorOptions,
 
     css_variable_machine: CssVariableMachine,
     candidate_machine: CandidateMachine,
@@ -63,8 +99,13 @@ pub struct Extractor<'a> {
 
 impl<'a> Extractor<'a> {
     pub fn new(input: &'a [u8]) -> Self {
+        Self::new_with_options(input, &DEFAULT_OPTIONS)
+    }
+
+    pub fn new_with_options(input: &'a [u8], options: &'a ExtractorOptions) -> Self {
         Self {
             cursor: cursor::Cursor::new(input),
+            options,
 
             css_variable_machine: Default::default(),
             candidate_machine: Default::default(),
@@ -137,6 +178,26 @@ impl<'a> Extractor<'a> {
             }
         }
 
+        if let Some(prefix) = &self.options.prefix {
+            extracted.retain(|extracted| match extracted {
+                Extracted::Candidate(candidate) => {
+                    candidate.len() > prefix.len()
+                        && candidate.starts_with(prefix.as_bytes())
+                        && candidate[prefix.len()] == b':'
+                }
+                Extracted::CssVariable(_) => true,
+            });
+        }
+
+        if let Some(allowlist) = &self.options.allowlist {
+            // The prefix is not a variant of the design system
+            let skip_variants = usize::from(self.options.prefix.is_some());
+            extracted.retain(|extracted| match extracted {
+                Extracted::Candidate(candidate) => allowlist.allows(candidate, skip_variants),
+                Extracted::CssVariable(_) => true,
+            });
+        }
+
         extracted
     }
 
@@ -232,7 +293,7 @@ fn drop_covered_spans(mut spans: Vec<Span>) -> Vec<Span> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Extracted, Extractor};
+    use super::{CandidateAllowlist, Extracted, Extractor, ExtractorOptions};
     use crate::throughput::Throughput;
     use pretty_assertions::assert_eq;
     use std::hint::black_box;
@@ -1146,4 +1207,70 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_extract_with_prefix() {
+        let options = ExtractorOptions {
+            prefix: Some("tw".into()),
+            ..Default::default()
+        };
+        let input = r#"<div class="tw:flex! tw:sm:block flex tw:[color:red] tw__flex tw-block" style="color: var(--tw-color)">"#;
+
+        let extracted = Extractor::new_with_options(input.as_bytes(), &options)
+            .extract()
+            .iter()
+            .map(|extracted| extracted.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            extracted,
+            vec![
+                "CssVariable(--tw-color)",
+                "Candidate(tw:flex!)",
+                "Candidate(tw:sm:block)",
+                "Candidate(tw:[color:red])",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_with_allowlist() {
+        let allowlist = CandidateAllowlist::new(
+            vec!["flex".into(), "bg".into()],
+            vec!["hover".into(), "data".into()],
+        );
+        let input = r#"<div class="flex hover:bg-red-500 focus:flex data-[open]:flex [color:red]" style="color: var(--my-color)">"#;
+
+        for (prefix, input, expected) in [
+            (
+                None,
+                input.to_string(),
+                vec![
+                    "CssVariable(--my-color)",
+                    "Candidate(flex)",
+                    "Candidate(hover:bg-red-500)",
+                    "Candidate(data-[open]:flex)",
+                    "Candidate([color:red])",
+                ],
+            ),
+            (
+                Some("tw".to_string()),
+                input.replace("class=\"", "class=\"tw:"),
+                vec!["CssVariable(--my-color)", "Candidate(tw:flex)"],
+            ),
+        ] {
+            let options = ExtractorOptions {
+                prefix,
+                allowlist: Some(allowlist.clone()),
+            };
+
+            let extracted = Extractor::new_with_options(input.as_bytes(), &options)
+                .extract()
+                .iter()
+                .map(|extracted| extracted.to_string())
+                .collect::<Vec<_>>();
+
+            assert_eq!(extracted, expected, "{input}");
+        }
+    }
 }
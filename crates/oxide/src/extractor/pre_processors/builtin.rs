@@ -0,0 +1,130 @@
+use crate::extractor::pre_processors::*;
+
+/// One of the pre-processors that ship with Tailwind CSS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuiltinPreProcessor {
+    /// Leave the content as-is
+    None,
+    Clojure,
+    Elixir,
+    Haml,
+    Json,
+    Markdown,
+    Pug,
+    Razor,
+    Ruby,
+    Rust,
+    Slim,
+    Svelte,
+    TemplateToolkit,
+    Twig,
+    Vue,
+}
+
+impl BuiltinPreProcessor {
+    /// The extensions every pre-processor is used for by default.
+    pub const DEFAULTS: &[(&str, Self)] = &[
+        ("clj", Self::Clojure),
+        ("cljs", Self::Clojure),
+        ("cljc", Self::Clojure),
+        ("heex", Self::Elixir),
+        ("eex", Self::Elixir),
+        ("ex", Self::Elixir),
+        ("exs", Self::Elixir),
+        ("cshtml", Self::Razor),
+        ("razor", Self::Razor),
+        ("haml", Self::Haml),
+        ("json", Self::Json),
+        ("jsonl", Self::Json),
+        ("ndjson", Self::Json),
+        ("md", Self::Markdown),
+        ("mdx", Self::Markdown),
+        ("pug", Self::Pug),
+        ("rb", Self::Ruby),
+        ("erb", Self::Ruby),
+        ("slim", Self::Slim),
+        ("slang", Self::Slim),
+        ("svelte", Self::Svelte),
+        ("rs", Self::Rust),
+        ("tt", Self::TemplateToolkit),
+        ("tt2", Self::TemplateToolkit),
+        ("tx", Self::TemplateToolkit),
+        ("twig", Self::Twig),
+        ("vue", Self::Vue),
+    ];
+
+    /// The pre-processor that is used for files with the given extension by default.
+    pub fn for_extension(extension: &str) -> Self {
+        Self::DEFAULTS
+            .iter()
+            .find(|(default, _)| *default == extension)
+            .map_or(Self::None, |(_, pre_processor)| *pre_processor)
+    }
+}
+
+impl PreProcessor for BuiltinPreProcessor {
+    fn cache_key(&self) -> Option<String> {
+        Some(format!("{self:?}"))
+    }
+
+    fn process(&self, content: &[u8]) -> Vec<u8> {
+        match self {
+            Self::None => content.to_vec(),
+            Self::Clojure => Clojure.process(content),
+            Self::Elixir => Elixir.process(content),
+            Self::Haml => Haml.process(content),
+            Self::Json => Json.process(content),
+            Self::Markdown => Markdown.process(content),
+            Self::Pug => Pug.process(content),
+            Self::Razor => Razor.process(content),
+            Self::Ruby => Ruby.process(content),
+            Self::Rust => Rust.process(content),
+            Self::Slim => Slim.process(content),
+            Self::Svelte => Svelte.process(content),
+            Self::TemplateToolkit => TemplateToolkit.process(content),
+            Self::Twig => Twig.process(content),
+            Self::Vue => Vue.process(content),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BuiltinPreProcessor;
+    use crate::extractor::pre_processors::pre_processor::moved_offset;
+    use crate::extractor::pre_processors::PreProcessor;
+
+    #[test]
+    fn test_builtin_pre_processors_keep_the_offsets() {
+        let inputs = [
+            "<div class=\"flex p-4\">Héllo 🔥</div>",
+            "<template lang=\"pug\">\n.bg-red-500.p-4 Héllo\n</template>",
+            "<template lang=\"haml\">\n%div.flex\n  = link_to 'Ünder', class: 'underline'\n</template>",
+            "html = <<~HTML\n  <div class=\"flex\">🔥</div>\nHTML\n",
+            "html = <<~SLIM\n  div.flex.p-4 🔥\nSLIM\n",
+            "%div.flex.p-4\n  = content_tag :p, 'Ü', class: %w[flex p-4]",
+            "{:class \"flex\" :div.p-4 #{\"🔥\"}}",
+            "<div class:px-4={active} {{ 'é'|upper }}>",
+            "# Title {.underline .p-4} **ü**",
+            "{\"class\": \"flex p-4\", \"é\": [\"🔥\"]}",
+            "[% IF x %]<div class=\"flex\">[% END %]",
+            "html! { div class=\"flex p-4\" { \"🔥\" } }",
+        ];
+
+        for (_, pre_processor) in BuiltinPreProcessor::DEFAULTS {
+            for input in inputs {
+                let output = pre_processor.process(input.as_bytes());
+                assert_eq!(
+                    output.len(),
+                    input.len(),
+                    "{pre_processor:?} changed the length of: {input}"
+                );
+                assert_eq!(
+                    moved_offset(input.as_bytes(), &output),
+                    None,
+                    "{pre_processor:?} moved content of: {input}"
+                );
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod builtin;
 pub mod clojure;
 pub mod elixir;
 pub mod haml;
@@ -14,6 +15,7 @@ pub mod template_toolkit;
 pub mod twig;
 pub mod vue;
 
+pub use builtin::*;
 pub use clojure::*;
 pub use elixir::*;
 pub use haml::*;
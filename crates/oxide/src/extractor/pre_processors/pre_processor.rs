@@ -1,8 +1,27 @@
-pub trait PreProcessor: Sized + Default {
+use tracing::event;
+
+/// Transforms the content of a file before candidates are extracted from it.
+///
+/// The output must have the exact same length as the input, and every byte must stay at its
+/// original position, such that byte offsets of candidates still point to the original content.
+/// Pre-processors only replace bytes, e.g.: `.flex` becomes ` flex`. Output with a different
+/// length, or with content that moved, is still used but logs a warning, see
+/// `process_checking_offsets`.
+pub trait PreProcessor: Send + Sync {
     fn process(&self, content: &[u8]) -> Vec<u8>;
 
+    /// Identifies what the pre-processor does, such that the persistent scan cache can be
+    /// invalidated when a different pre-processor is used. The cache is not used when any of the
+    /// registered pre-processors doesn't have a key.
+    fn cache_key(&self) -> Option<String> {
+        None
+    }
+
     #[cfg(test)]
-    fn test(input: &str, expected: &str) {
+    fn test(input: &str, expected: &str)
+    where
+        Self: Sized + Default,
+    {
         use pretty_assertions::assert_eq;
 
         let input = input.as_bytes();
@@ -17,15 +36,19 @@ pub trait PreProcessor: Sized + Default {
         let actual = String::from_utf8_lossy(&actual);
         let expected = String::from_utf8_lossy(expected);
 
-        // The input and output should have the exact same length.
+        // The input and output should have the exact same length, and keep all content in place.
         assert_eq!(input.len(), actual.len());
         assert_eq!(actual.len(), expected.len());
+        assert_eq!(moved_offset(input.as_bytes(), actual.as_bytes()), None);
 
         assert_eq!(actual, expected);
     }
 
     #[cfg(test)]
-    fn test_extract_exact(input: &str, expected: Vec<&str>) {
+    fn test_extract_exact(input: &str, expected: Vec<&str>)
+    where
+        Self: Sized + Default,
+    {
         use crate::extractor::{Extracted, Extractor};
 
         let input = input.as_bytes();
@@ -51,7 +74,10 @@ pub trait PreProcessor: Sized + Default {
     }
 
     #[cfg(test)]
-    fn test_extract_contains(input: &str, expected: Vec<&str>) {
+    fn test_extract_contains(input: &str, expected: Vec<&str>)
+    where
+        Self: Sized + Default,
+    {
         use crate::extractor::{Extracted, Extractor};
 
         let input = input.as_bytes();
@@ -85,7 +111,10 @@ pub trait PreProcessor: Sized + Default {
     }
 
     #[cfg(test)]
-    fn extract_annotated(input: &[u8]) -> String {
+    fn extract_annotated(input: &[u8]) -> String
+    where
+        Self: Sized + Default,
+    {
         use crate::extractor::{Extracted, Extractor};
         use std::collections::BTreeMap;
         use unicode_width::UnicodeWidthStr;
@@ -161,6 +190,47 @@ pub trait PreProcessor: Sized + Default {
     }
 }
 
+/// Runs the `pre_processor`, and warns when the output doesn't keep the offsets intact, because
+/// offsets in the output would not match the original content.
+///
+/// This is a bug in the pre-processor, but the output is still used, falling back to the original
+/// content would lose the candidates the pre-processor exposes. Pre-processors can be registered
+/// by embedders, so this only logs a warning. The built-in pre-processors are checked by their
+/// tests, see `PreProcessor::test`.
+pub fn process_checking_offsets(pre_processor: &dyn PreProcessor, content: &[u8]) -> Vec<u8> {
+    let processed = pre_processor.process(content);
+
+    let reason = if processed.len() != content.len() {
+        format!(
+            "the length changed from {} to {} bytes",
+            content.len(),
+            processed.len()
+        )
+    } else if let Some(offset) = moved_offset(content, &processed) {
+        format!("content moved to offset {offset}, which was whitespace before")
+    } else {
+        return processed;
+    };
+
+    event!(
+        tracing::Level::WARN,
+        "Pre-processed content doesn't match the original offsets, {}",
+        reason
+    );
+
+    processed
+}
+
+/// The first offset where the processed content has a non-whitespace byte, while the original
+/// content has whitespace. Pre-processors replace bytes in place, e.g.: `.flex` becomes ` flex`,
+/// but content that shifts (even when the length is the same) ends up where whitespace used to be.
+pub(crate) fn moved_offset(content: &[u8], processed: &[u8]) -> Option<usize> {
+    content
+        .iter()
+        .zip(processed)
+        .position(|(before, after)| before.is_ascii_whitespace() && !after.is_ascii_whitespace())
+}
+
 #[cfg(test)]
 fn byte_offset_to_line_and_column(input: &[u8], offset: usize) -> (usize, usize) {
     use unicode_width::UnicodeWidthStr;
@@ -184,3 +254,40 @@ fn byte_offset_to_line_and_column(input: &[u8], offset: usize) -> (usize, usize)
 
     (line, column)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{moved_offset, process_checking_offsets, PreProcessor};
+
+    /// Removes the first byte, and pads the end to keep the length
+    struct Shift;
+
+    impl PreProcessor for Shift {
+        fn process(&self, content: &[u8]) -> Vec<u8> {
+            [&content[1..], b" "].concat()
+        }
+    }
+
+    #[test]
+    fn test_moved_offset() {
+        assert_eq!(moved_offset(b".flex .p-4", b" flex  p-4"), None);
+        assert_eq!(moved_offset(b"@(\"@\")md:flex", b"     @md:flex"), None);
+        assert_eq!(moved_offset(b".flex .p-4", b"flex .p-4 "), Some(5));
+    }
+
+    /// Removes the first byte
+    struct Shorten;
+
+    impl PreProcessor for Shorten {
+        fn process(&self, content: &[u8]) -> Vec<u8> {
+            content[1..].to_vec()
+        }
+    }
+
+    #[test]
+    fn test_output_that_moves_content_is_kept() {
+        let content = b".flex .p-4";
+        assert_eq!(process_checking_offsets(&Shift, content), b"flex .p-4 ");
+        assert_eq!(process_checking_offsets(&Shorten, content), b"flex .p-4");
+    }
+}
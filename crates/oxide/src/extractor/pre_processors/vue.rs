@@ -1,6 +1,6 @@
 use crate::extractor::pre_processors::pre_processor::PreProcessor;
 use crate::scanner::pre_process_input;
-use bstr::ByteSlice;
+use bstr::ByteVec;
 use regex::Regex;
 use std::sync;
 
@@ -17,12 +17,13 @@ impl PreProcessor for Vue {
 
         // Only process template tags if content is valid UTF-8
         if let Ok(content_as_str) = std::str::from_utf8(content) {
-            for (_, [lang, body]) in TEMPLATE_REGEX
-                .captures_iter(content_as_str)
-                .map(|c| c.extract())
-            {
-                let replaced = pre_process_input(body.as_bytes().to_vec(), lang);
-                result = result.replace(body, replaced);
+            for captures in TEMPLATE_REGEX.captures_iter(content_as_str) {
+                let lang = captures.get(1).unwrap().as_str();
+                let body = captures.get(2).unwrap();
+
+                // Replace the body in place, such that offsets stay the same
+                let replaced = pre_process_input(body.as_str().as_bytes().to_vec(), lang);
+                result.replace_range(body.range(), replaced);
             }
         }
 
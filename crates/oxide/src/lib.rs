@@ -6,7 +6,24 @@ pub mod paths;
 pub mod scanner;
 pub mod throughput;
 
+pub use extractor::allowlist::CandidateAllowlist;
+pub use extractor::candidate::{Candidate, CandidateValue, UtilityKind, ValueKind};
+pub use extractor::pre_processors::{BuiltinPreProcessor, PreProcessor};
+pub use extractor::ExtractedKind;
 pub use glob::GlobEntry;
+pub use scanner::auto_source_detection::{AutoSourceDetectionOptions, RuleListChanges};
+pub use scanner::explain::{Explanation, MatchedRule, RuleOrigin, Verdict};
+pub use scanner::limits::SkipReason;
+pub use scanner::pre_processing::PreProcessorMapping;
 pub use scanner::sources::PublicSourceEntry;
+pub use scanner::stats::{PhaseStats, ScanStats};
+pub use scanner::usage::{CandidateUsage, ExtensionUsage, UsageReport};
+#[cfg(not(target_family = "wasm"))]
+pub use scanner::watcher::{ScannerWatcher, WatchOptions};
+pub use scanner::CandidateLocation;
+pub use scanner::CandidatePosition;
+pub use scanner::ChangeDetection;
 pub use scanner::ChangedContent;
+pub use scanner::ScanDiff;
 pub use scanner::Scanner;
+pub use scanner::ScannerOptions;
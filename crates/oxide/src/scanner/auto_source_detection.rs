@@ -1,7 +1,70 @@
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use std::sync;
 
-/// All the default rules for auto source detection.
+static DEFAULT: sync::LazyLock<AutoSourceDetection> =
+    sync::LazyLock::new(|| AutoSourceDetection::new(&AutoSourceDetectionOptions::default()));
+
+static IGNORED_CONTENT_DIRS: sync::LazyLock<Vec<&'static str>> =
+    sync::LazyLock::new(|| fixture(include_str!("fixtures/ignored-content-dirs.txt")));
+
+static IGNORED_EXTENSIONS: sync::LazyLock<Vec<&'static str>> =
+    sync::LazyLock::new(|| fixture(include_str!("fixtures/ignored-extensions.txt")));
+
+static BINARY_EXTENSIONS: sync::LazyLock<Vec<&'static str>> =
+    sync::LazyLock::new(|| fixture(include_str!("fixtures/binary-extensions.txt")));
+
+static IGNORED_FILES: sync::LazyLock<Vec<&'static str>> =
+    sync::LazyLock::new(|| fixture(include_str!("fixtures/ignored-files.txt")));
+
+fn fixture(contents: &'static str) -> Vec<&'static str> {
+    contents.trim().lines().collect()
+}
+
+/// Entries to add to, or remove from one of the lists of auto source detection rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleListChanges {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+impl RuleListChanges {
+    /// The built-in entries are kept as they are, `added` maps every added entry.
+    fn apply(&self, defaults: &[&str], added: impl Fn(&str) -> String) -> Vec<String> {
+        let mut list = defaults
+            .iter()
+            .filter(|entry| !self.remove.iter().any(|removed| removed == *entry))
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>();
+
+        for entry in self.add.iter().map(|entry| added(entry)) {
+            if !list.contains(&entry) {
+                list.push(entry);
+            }
+        }
+
+        list
+    }
+
+    /// The entries of the list as globs. The built-in entries are globs already (e.g.: `.env.*`),
+    /// the added entries are matched literally.
+    fn apply_as_globs(&self, defaults: &[&str]) -> Vec<String> {
+        self.apply(defaults, escape)
+    }
+}
+
+/// Changes to the built-in auto source detection rules, e.g.: to stop ignoring a `.venv` folder,
+/// or to ignore a `dist-storybook` folder.
+///
+/// Extensions are written without the leading `.`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AutoSourceDetectionOptions {
+    pub ignored_content_dirs: RuleListChanges,
+    pub ignored_extensions: RuleListChanges,
+    pub binary_extensions: RuleListChanges,
+    pub ignored_files: RuleListChanges,
+}
+
+/// The auto source detection rules, with the changes from `AutoSourceDetectionOptions` applied.
 ///
 /// This includes:
 ///
@@ -9,63 +72,203 @@ use std::sync;
 /// - Ignoring file extensions we definitely don't want to include like `.css` and `.scss`
 /// - Ignoring common binary file extensions like `.png` and `.jpg`
 /// - Ignoring common files like `yarn.lock` and `package-lock.json`
-///
-pub static RULES: sync::LazyLock<Vec<Gitignore>> = sync::LazyLock::new(|| {
-    let mut builder = GitignoreBuilder::new("");
-
-    builder.add_line(None, &IGNORED_CONTENT_DIRS_GLOB).unwrap();
-    builder.add_line(None, &IGNORED_EXTENSIONS_GLOB).unwrap();
-    builder.add_line(None, &IGNORED_FILES_GLOB).unwrap();
-
-    // Ensure these rules do not match on folder names
-    let mut file_only_builder = GitignoreBuilder::new("");
-    file_only_builder
-        .only_on_files(true)
-        .add_line(None, &BINARY_EXTENSIONS_GLOB)
-        .unwrap();
-
-    vec![builder.build().unwrap(), file_only_builder.build().unwrap()]
-});
-
-pub static IGNORED_CONTENT_DIRS: sync::LazyLock<Vec<&'static str>> = sync::LazyLock::new(|| {
-    include_str!("fixtures/ignored-content-dirs.txt")
-        .trim()
-        .lines()
-        .collect()
-});
-
-static IGNORED_CONTENT_DIRS_GLOB: sync::LazyLock<String> =
-    sync::LazyLock::new(|| format!("{{{}}}/", IGNORED_CONTENT_DIRS.join(",")));
-
-static IGNORED_EXTENSIONS_GLOB: sync::LazyLock<String> = sync::LazyLock::new(|| {
-    format!(
-        "*.{{{}}}",
-        include_str!("fixtures/ignored-extensions.txt")
-            .trim()
-            .lines()
-            .collect::<Vec<&str>>()
-            .join(",")
-    )
-});
-
-pub static BINARY_EXTENSIONS_GLOB: sync::LazyLock<String> = sync::LazyLock::new(|| {
-    format!(
-        "*.{{{}}}",
-        include_str!("fixtures/binary-extensions.txt")
-            .trim()
-            .lines()
-            .collect::<Vec<&str>>()
-            .join(",")
-    )
-});
-
-static IGNORED_FILES_GLOB: sync::LazyLock<String> = sync::LazyLock::new(|| {
-    format!(
-        "{{{}}}",
-        include_str!("fixtures/ignored-files.txt")
-            .trim()
-            .lines()
-            .collect::<Vec<&str>>()
-            .join(",")
-    )
-});
+#[derive(Debug, Clone)]
+pub struct AutoSourceDetection {
+    pub ignored_content_dirs: Vec<String>,
+
+    /// The globs of each list, `None` when the list is empty
+    pub ignored_content_dirs_glob: Option<String>,
+    pub ignored_extensions_glob: Option<String>,
+    pub binary_extensions_glob: Option<String>,
+    pub ignored_files_glob: Option<String>,
+
+    pub rules: Vec<Gitignore>,
+}
+
+impl Default for AutoSourceDetection {
+    fn default() -> Self {
+        DEFAULT.clone()
+    }
+}
+
+impl AutoSourceDetection {
+    /// Added entries are matched literally, characters like `*` or `{` don't have a special
+    /// meaning. This means that the rules can always be built.
+    pub fn new(options: &AutoSourceDetectionOptions) -> Self {
+        let ignored_content_dirs = options
+            .ignored_content_dirs
+            .apply(&IGNORED_CONTENT_DIRS, str::to_string);
+
+        let ignored_content_dirs_glob = glob(
+            options
+                .ignored_content_dirs
+                .apply_as_globs(&IGNORED_CONTENT_DIRS),
+            |list| format!("{{{list}}}/"),
+        );
+        let ignored_extensions_glob = glob(
+            options
+                .ignored_extensions
+                .apply_as_globs(&IGNORED_EXTENSIONS),
+            |list| format!("*.{{{list}}}"),
+        );
+        let binary_extensions_glob = glob(
+            options.binary_extensions.apply_as_globs(&BINARY_EXTENSIONS),
+            |list| format!("*.{{{list}}}"),
+        );
+        let ignored_files_glob = glob(
+            options.ignored_files.apply_as_globs(&IGNORED_FILES),
+            |list| format!("{{{list}}}"),
+        );
+
+        let mut builder = GitignoreBuilder::new("");
+        for glob in [
+            &ignored_content_dirs_glob,
+            &ignored_extensions_glob,
+            &ignored_files_glob,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            builder.add_line(None, glob).unwrap();
+        }
+
+        // Ensure these rules do not match on folder names
+        let mut file_only_builder = GitignoreBuilder::new("");
+        file_only_builder.only_on_files(true);
+        if let Some(glob) = &binary_extensions_glob {
+            file_only_builder.add_line(None, glob).unwrap();
+        }
+
+        Self {
+            ignored_content_dirs,
+            ignored_content_dirs_glob,
+            ignored_extensions_glob,
+            binary_extensions_glob,
+            ignored_files_glob,
+            rules: vec![builder.build().unwrap(), file_only_builder.build().unwrap()],
+        }
+    }
+}
+
+/// A glob that matches any of the entries of the list, `None` when the list is empty.
+fn glob(entries: Vec<String>, format: impl Fn(String) -> String) -> Option<String> {
+    let entries = entries
+        .into_iter()
+        .filter(|entry| !entry.is_empty())
+        .collect::<Vec<_>>();
+
+    (!entries.is_empty()).then(|| format(entries.join(",")))
+}
+
+/// Escape every character that has a special meaning in a (brace) glob.
+fn escape(entry: &str) -> String {
+    let mut escaped = String::with_capacity(entry.len());
+    for c in entry.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[' | ']' | '{' | '}' | ',' | '!') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoSourceDetection, AutoSourceDetectionOptions, RuleListChanges};
+    use std::path::Path;
+
+    #[test]
+    fn test_rule_list_changes() {
+        let rules = AutoSourceDetection::new(&AutoSourceDetectionOptions {
+            ignored_content_dirs: RuleListChanges {
+                add: vec!["dist-storybook".into()],
+                remove: vec![".venv".into()],
+            },
+            binary_extensions: RuleListChanges {
+                add: vec![],
+                remove: vec!["png".into()],
+            },
+            ..Default::default()
+        });
+
+        let ignored = |path: &str, is_dir: bool| {
+            rules
+                .rules
+                .iter()
+                .any(|rule| rule.matched(Path::new(path), is_dir).is_ignore())
+        };
+
+        assert!(ignored("node_modules", true));
+        assert!(ignored("dist-storybook", true));
+        assert!(!ignored(".venv", true));
+        assert!(ignored("logo.jpg", false));
+        assert!(!ignored("logo.png", false));
+        assert!(ignored("styles.scss", false));
+    }
+
+    #[test]
+    fn test_entries_are_matched_literally() {
+        let rules = AutoSourceDetection::new(&AutoSourceDetectionOptions {
+            ignored_content_dirs: RuleListChanges {
+                add: vec![
+                    "a,b".into(),
+                    "{c}".into(),
+                    "[d]".into(),
+                    "e*".into(),
+                    "".into(),
+                ],
+                remove: vec![],
+            },
+            ignored_files: RuleListChanges {
+                add: vec!["f\\g?.txt".into()],
+                remove: vec![],
+            },
+            ..Default::default()
+        });
+
+        let ignored = |path: &str, is_dir: bool| {
+            rules
+                .rules
+                .iter()
+                .any(|rule| rule.matched(Path::new(path), is_dir).is_ignore())
+        };
+
+        assert!(ignored("a,b", true));
+        assert!(ignored("{c}", true));
+        assert!(ignored("[d]", true));
+        assert!(ignored("e*", true));
+        assert!(ignored("f\\g?.txt", false));
+
+        assert!(!ignored("a", true));
+        assert!(!ignored("b", true));
+        assert!(!ignored("c", true));
+        assert!(!ignored("d", true));
+        assert!(!ignored("ee", true));
+        assert!(!ignored("fg1.txt", false));
+    }
+
+    #[test]
+    fn test_built_in_entries_are_globs() {
+        let rules = AutoSourceDetection::new(&AutoSourceDetectionOptions {
+            ignored_files: RuleListChanges {
+                add: vec!["custom.*".into()],
+                remove: vec![],
+            },
+            ..Default::default()
+        });
+
+        let ignored = |path: &str| {
+            rules
+                .rules
+                .iter()
+                .any(|rule| rule.matched(Path::new(path), false).is_ignore())
+        };
+
+        assert!(ignored(".env"));
+        assert!(ignored(".env.local"));
+        assert!(ignored(".env.production"));
+        assert!(ignored("custom.*"));
+        assert!(!ignored("custom.html"));
+    }
+}
@@ -0,0 +1,311 @@
+use crate::scanner::candidate_index::CandidateIndex;
+use crate::scanner::sources::Sources;
+use crate::scanner::RACY_MTIME_WINDOW;
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::event;
+
+/// Bump this when the format of the cache, or the way candidates are extracted, changes. Caches
+/// written by a different version are ignored.
+const CACHE_VERSION: u32 = 3;
+
+/// Everything we know about the contents of a file without reading it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub mtime: Option<SystemTime>,
+    pub size: u64,
+    pub hash: u64,
+}
+
+impl FileStamp {
+    pub fn new(mtime: Option<SystemTime>, content: &[u8]) -> Self {
+        Self {
+            mtime,
+            size: content.len() as u64,
+            hash: hash_content(content),
+        }
+    }
+}
+
+/// A fast, stable hash of the contents of a file.
+pub fn hash_content(content: &[u8]) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(content)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    file: PathBuf,
+    stamp: FileStamp,
+    candidates: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheContents {
+    version: u32,
+
+    /// Hash of every option that changes which candidates are extracted, see `Scanner::cache_key`
+    options: u64,
+
+    /// When the cache was written
+    written: SystemTime,
+
+    files: Vec<CachedFile>,
+}
+
+/// Candidates of every scanned file, persisted to disk so a new process doesn't have to read and
+/// extract files that didn't change since the last run.
+#[derive(Debug, Clone)]
+pub struct ScanCache {
+    files: FxHashMap<PathBuf, CachedFile>,
+
+    /// When the cache was written
+    written: SystemTime,
+}
+
+impl ScanCache {
+    /// The cache file for a set of sources. Every set of sources gets its own file, so multiple
+    /// scanners in the same project don't overwrite each other's cache.
+    pub fn file_for(dir: &Path, sources: &Sources) -> PathBuf {
+        let key = sources
+            .iter()
+            .map(|source| format!("{source:?}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        dir.join(format!(
+            "scanner-{:016x}.json",
+            hash_content(key.as_bytes())
+        ))
+    }
+
+    /// Load the cache from disk. A missing, unreadable or outdated cache, or a cache that was
+    /// written with different `options`, is treated as empty.
+    #[tracing::instrument(skip_all)]
+    pub fn load(path: &Path, options: u64) -> Option<Self> {
+        let file = File::open(path).ok()?;
+
+        let contents: CacheContents = match serde_json::from_reader(BufReader::new(file)) {
+            Ok(contents) => contents,
+            Err(err) => {
+                event!(
+                    tracing::Level::WARN,
+                    "Ignoring invalid scan cache {:?}: {}",
+                    path,
+                    err
+                );
+                return None;
+            }
+        };
+
+        if contents.version != CACHE_VERSION {
+            event!(
+                tracing::Level::INFO,
+                "Ignoring outdated scan cache {:?}",
+                path
+            );
+            return None;
+        }
+
+        if contents.options != options {
+            event!(
+                tracing::Level::INFO,
+                "Ignoring scan cache {:?}, it was written with different options",
+                path
+            );
+            return None;
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "Loaded {} file(s) from scan cache {:?}",
+            contents.files.len(),
+            path
+        );
+
+        Some(Self {
+            files: contents
+                .files
+                .into_iter()
+                .map(|cached| (cached.file.clone(), cached))
+                .collect(),
+            written: contents.written,
+        })
+    }
+
+    /// Candidates of `file` when its mtime and size didn't change since it was cached.
+    ///
+    /// Files modified within `RACY_MTIME_WINDOW` of writing the cache could have been modified
+    /// after they were read without changing their mtime, on file systems with a coarse mtime
+    /// resolution. Their contents have to be compared with `same_content` instead.
+    pub fn unchanged(
+        &self,
+        file: &Path,
+        mtime: Option<SystemTime>,
+        size: u64,
+    ) -> Option<(FileStamp, FxHashSet<String>)> {
+        let cached = self.files.get(file)?;
+        let mtime = mtime?;
+        if cached.stamp.mtime != Some(mtime) || cached.stamp.size != size {
+            return None;
+        }
+
+        if mtime + RACY_MTIME_WINDOW >= self.written {
+            return None;
+        }
+
+        Some((cached.stamp, cached.candidates.iter().cloned().collect()))
+    }
+
+    /// Candidates of `file` when its contents didn't change since it was cached. This is useful
+    /// when the mtime changed without touching the contents, e.g.: on a fresh checkout.
+    pub fn same_content(&self, file: &Path, stamp: &FileStamp) -> Option<FxHashSet<String>> {
+        let cached = self.files.get(file)?;
+        if cached.stamp.size != stamp.size || cached.stamp.hash != stamp.hash {
+            return None;
+        }
+
+        Some(cached.candidates.iter().cloned().collect())
+    }
+
+    /// Write the candidates of every file we have a stamp for to disk.
+    #[tracing::instrument(skip_all)]
+    pub fn save(
+        path: &Path,
+        options: u64,
+        index: &CandidateIndex,
+        stamps: &FxHashMap<PathBuf, FileStamp>,
+    ) -> std::io::Result<()> {
+        let mut files = index
+            .files()
+            .filter_map(|(file, candidates)| {
+                let stamp = stamps.get(file)?;
+                let mut candidates = candidates.iter().cloned().collect::<Vec<_>>();
+                candidates.sort_unstable();
+
+                Some(CachedFile {
+                    file: file.clone(),
+                    stamp: *stamp,
+                    candidates,
+                })
+            })
+            .collect::<Vec<_>>();
+        files.sort_unstable_by(|a, b| a.file.cmp(&b.file));
+
+        let Some(dir) = path.parent() else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(dir)?;
+
+        // Ensure the cache is never committed, nor picked up by the scanner itself. An existing
+        // `.gitignore` is left untouched.
+        if let Ok(mut file) = File::create_new(dir.join(".gitignore")) {
+            _ = file.write_all(b"*\n");
+        }
+
+        // Write to a temporary file first, so a concurrent process never reads a partial cache.
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        {
+            let mut writer = BufWriter::new(File::create(&tmp)?);
+            serde_json::to_writer(
+                &mut writer,
+                &CacheContents {
+                    version: CACHE_VERSION,
+                    options,
+                    written: SystemTime::now(),
+                    files,
+                },
+            )?;
+            writer.flush()?;
+        }
+        std::fs::rename(&tmp, path)?;
+
+        event!(tracing::Level::INFO, "Wrote scan cache {:?}", path);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileStamp, ScanCache};
+    use crate::scanner::candidate_index::{CandidateChanges, CandidateIndex};
+    use fxhash::FxHashMap;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache/scanner.json");
+
+        let file = PathBuf::from("/project/index.html");
+        let mtime = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000));
+        let stamp = FileStamp::new(mtime, b"flex underline");
+
+        let mut index = CandidateIndex::default();
+        index.update_file(
+            file.clone(),
+            ["flex".to_string(), "underline".to_string()]
+                .into_iter()
+                .collect(),
+            &mut CandidateChanges::default(),
+        );
+
+        let mut stamps = FxHashMap::default();
+        stamps.insert(file.clone(), stamp);
+
+        ScanCache::save(&path, 1, &index, &stamps).unwrap();
+        assert!(dir.path().join("cache/.gitignore").exists());
+
+        // A cache written with different options is ignored
+        assert!(ScanCache::load(&path, 2).is_none());
+
+        let cache = ScanCache::load(&path, 1).unwrap();
+
+        let (_, candidates) = cache.unchanged(&file, mtime, stamp.size).unwrap();
+        assert_eq!(candidates.len(), 2);
+
+        // An mtime too close to when the cache was written can't be trusted
+        let racy = SystemTime::now();
+        let mut stamps = FxHashMap::default();
+        stamps.insert(
+            file.clone(),
+            FileStamp {
+                mtime: Some(racy),
+                ..stamp
+            },
+        );
+        ScanCache::save(&path, 1, &index, &stamps).unwrap();
+        let racy_cache = ScanCache::load(&path, 1).unwrap();
+        assert!(racy_cache
+            .unchanged(&file, Some(racy), stamp.size)
+            .is_none());
+        assert!(racy_cache.same_content(&file, &stamp).is_some());
+
+        // A different mtime invalidates the entry, unless the contents are the same
+        let later = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(2_000));
+        assert!(cache.unchanged(&file, later, stamp.size).is_none());
+        assert!(cache
+            .same_content(&file, &FileStamp::new(later, b"flex underline"))
+            .is_some());
+        assert!(cache
+            .same_content(&file, &FileStamp::new(later, b"flex underlined"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_invalid_caches_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scanner.json");
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(ScanCache::load(&path, 0).is_none());
+
+        std::fs::write(&path, r#"{ "version": 0, "options": 0, "files": [] }"#).unwrap();
+        assert!(ScanCache::load(&path, 0).is_none());
+    }
+}
@@ -0,0 +1,193 @@
+use fxhash::{FxHashMap, FxHashSet};
+use std::path::{Path, PathBuf};
+
+/// Keeps track of which files produced which candidates.
+///
+/// Every candidate is reference counted by the files that contain it, which allows us to drop a
+/// candidate as soon as the last file that mentions it changes or disappears. Candidates from raw
+/// content (`ChangedContent::Content`) don't belong to a file, and are kept forever.
+#[derive(Debug, Clone, Default)]
+pub struct CandidateIndex {
+    /// All known candidates, with the number of files (or pieces of raw content) they were found in
+    refs: FxHashMap<String, usize>,
+
+    /// Candidates found in each scanned file, keyed by the canonical path of the file
+    files: FxHashMap<PathBuf, FxHashSet<String>>,
+}
+
+/// The net effect of a set of updates to the `CandidateIndex`.
+///
+/// A candidate that disappears from one file and shows up in another one during the same update
+/// is neither added nor removed.
+#[derive(Debug, Clone, Default)]
+pub struct CandidateChanges {
+    pub added: FxHashSet<String>,
+    pub removed: FxHashSet<String>,
+}
+
+impl CandidateIndex {
+    pub fn contains(&self, candidate: &str) -> bool {
+        self.refs.contains_key(candidate)
+    }
+
+    pub fn candidates(&self) -> impl Iterator<Item = &String> {
+        self.refs.keys()
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&PathBuf, &FxHashSet<String>)> {
+        self.files.iter()
+    }
+
+    /// Replace the candidates of `file` with a freshly extracted set.
+    pub fn update_file(
+        &mut self,
+        file: PathBuf,
+        candidates: FxHashSet<String>,
+        changes: &mut CandidateChanges,
+    ) {
+        let previous = self.files.remove(&file).unwrap_or_default();
+
+        for candidate in candidates.difference(&previous) {
+            self.retain(candidate, changes);
+        }
+
+        for candidate in previous.difference(&candidates) {
+            self.release(candidate, changes);
+        }
+
+        self.files.insert(file, candidates);
+    }
+
+    /// Forget about a file, e.g.: because it was deleted.
+    pub fn remove_file(&mut self, file: &Path, changes: &mut CandidateChanges) {
+        let Some(previous) = self.files.remove(file) else {
+            return;
+        };
+
+        for candidate in &previous {
+            self.release(candidate, changes);
+        }
+    }
+
+    /// Add candidates that don't belong to a file. These are never released.
+    pub fn add_content(&mut self, candidates: FxHashSet<String>, changes: &mut CandidateChanges) {
+        for candidate in &candidates {
+            self.retain(candidate, changes);
+        }
+    }
+
+    /// Forget about all files that don't pass the `keep` check, returns the removed files.
+    pub fn retain_files<F>(&mut self, mut keep: F, changes: &mut CandidateChanges) -> Vec<PathBuf>
+    where
+        F: FnMut(&Path) -> bool,
+    {
+        let removed = self
+            .files
+            .keys()
+            .filter(|file| !keep(file))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for file in &removed {
+            self.remove_file(file, changes);
+        }
+
+        removed
+    }
+
+    fn retain(&mut self, candidate: &String, changes: &mut CandidateChanges) {
+        match self.refs.get_mut(candidate) {
+            Some(count) => *count += 1,
+            None => {
+                self.refs.insert(candidate.clone(), 1);
+                if !changes.removed.remove(candidate) {
+                    changes.added.insert(candidate.clone());
+                }
+            }
+        }
+    }
+
+    fn release(&mut self, candidate: &String, changes: &mut CandidateChanges) {
+        let Some(count) = self.refs.get_mut(candidate) else {
+            return;
+        };
+
+        *count -= 1;
+        if *count == 0 {
+            self.refs.remove(candidate);
+            if !changes.added.remove(candidate) {
+                changes.removed.insert(candidate.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CandidateChanges, CandidateIndex};
+    use fxhash::FxHashSet;
+    use std::path::PathBuf;
+
+    fn set(candidates: &[&str]) -> FxHashSet<String> {
+        candidates.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn sorted(candidates: &FxHashSet<String>) -> Vec<&str> {
+        let mut candidates = candidates.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+        candidates.sort();
+        candidates
+    }
+
+    #[test]
+    fn test_candidates_are_released_with_the_last_file() {
+        let mut index = CandidateIndex::default();
+        let mut changes = CandidateChanges::default();
+
+        index.update_file(
+            PathBuf::from("a.html"),
+            set(&["flex", "underline"]),
+            &mut changes,
+        );
+        index.update_file(PathBuf::from("b.html"), set(&["flex"]), &mut changes);
+        assert_eq!(sorted(&changes.added), vec!["flex", "underline"]);
+
+        let mut changes = CandidateChanges::default();
+        index.remove_file(&PathBuf::from("a.html"), &mut changes);
+        assert_eq!(sorted(&changes.removed), vec!["underline"]);
+        assert!(index.contains("flex"));
+
+        let mut changes = CandidateChanges::default();
+        index.update_file(PathBuf::from("b.html"), set(&["block"]), &mut changes);
+        assert_eq!(sorted(&changes.added), vec!["block"]);
+        assert_eq!(sorted(&changes.removed), vec!["flex"]);
+    }
+
+    #[test]
+    fn test_moving_a_candidate_between_files_is_not_a_change() {
+        let mut index = CandidateIndex::default();
+        let mut changes = CandidateChanges::default();
+
+        index.update_file(PathBuf::from("a.html"), set(&["flex"]), &mut changes);
+
+        let mut changes = CandidateChanges::default();
+        index.update_file(PathBuf::from("a.html"), set(&[]), &mut changes);
+        index.update_file(PathBuf::from("b.html"), set(&["flex"]), &mut changes);
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.is_empty());
+        assert!(index.contains("flex"));
+    }
+
+    #[test]
+    fn test_raw_content_is_never_released() {
+        let mut index = CandidateIndex::default();
+        let mut changes = CandidateChanges::default();
+
+        index.add_content(set(&["flex"]), &mut changes);
+        index.update_file(PathBuf::from("a.html"), set(&["flex"]), &mut changes);
+        index.remove_file(&PathBuf::from("a.html"), &mut changes);
+
+        assert!(index.contains("flex"));
+        assert_eq!(sorted(&changes.added), vec!["flex"]);
+        assert!(changes.removed.is_empty());
+    }
+}
@@ -1,4 +1,3 @@
-use crate::scanner::auto_source_detection::IGNORED_CONTENT_DIRS;
 use crate::GlobEntry;
 use fxhash::FxHashSet;
 use globwalk::DirEntry;
@@ -32,6 +31,7 @@ pub fn resolve_globs(
     base: PathBuf,
     dirs: &FxHashSet<PathBuf>,
     extensions: &FxHashSet<String>,
+    ignored_content_dirs: &[String],
 ) -> Vec<GlobEntry> {
     // A list of known extensions + a list of extensions we found in the project.
     let mut found_extensions: FxHashSet<String> =
@@ -100,10 +100,10 @@ pub fn resolve_globs(
             continue;
         }
 
-        if IGNORED_CONTENT_DIRS
+        if ignored_content_dirs
             .iter()
             .any(|dir| match path.file_name() {
-                Some(name) => name == *dir,
+                Some(name) => name == dir.as_str(),
                 None => false,
             })
         {
@@ -0,0 +1,169 @@
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// The encoding of a file, based on its byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8 without a byte order mark, or any other ASCII compatible encoding
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn detect(content: &[u8]) -> Self {
+        if content.starts_with(UTF8_BOM) {
+            Self::Utf8Bom
+        } else if content.starts_with(UTF16_LE_BOM) {
+            Self::Utf16Le
+        } else if content.starts_with(UTF16_BE_BOM) {
+            Self::Utf16Be
+        } else {
+            Self::Utf8
+        }
+    }
+}
+
+/// A run of characters that have the same length in the decoded and in the original content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    decoded: usize,
+    original: usize,
+    decoded_len: usize,
+    original_len: usize,
+}
+
+/// Maps byte offsets in decoded content back to byte offsets in the original content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OffsetMap {
+    segments: Vec<Segment>,
+}
+
+impl OffsetMap {
+    fn push(&mut self, decoded: usize, original: usize, decoded_len: usize, original_len: usize) {
+        match self.segments.last() {
+            // Characters are pushed in order, so the same lengths continue the last segment
+            Some(last) if last.decoded_len == decoded_len && last.original_len == original_len => {}
+            _ => self.segments.push(Segment {
+                decoded,
+                original,
+                decoded_len,
+                original_len,
+            }),
+        }
+    }
+
+    /// The offset in the original content, for an offset at a character boundary in the decoded
+    /// content.
+    pub fn original(&self, offset: usize) -> usize {
+        let idx = self
+            .segments
+            .partition_point(|segment| segment.decoded <= offset);
+
+        match idx.checked_sub(1).map(|idx| self.segments[idx]) {
+            Some(segment) => {
+                let chars = (offset - segment.decoded) / segment.decoded_len;
+                segment.original + chars * segment.original_len
+            }
+            None => offset,
+        }
+    }
+
+    /// The offset in the decoded content, for an offset at a character boundary in the original
+    /// content. This is the inverse of `original`.
+    pub fn decoded(&self, offset: usize) -> usize {
+        let idx = self
+            .segments
+            .partition_point(|segment| segment.original <= offset);
+
+        match idx.checked_sub(1).map(|idx| self.segments[idx]) {
+            Some(segment) => {
+                let chars = (offset - segment.original) / segment.original_len;
+                segment.decoded + chars * segment.decoded_len
+            }
+            None => offset,
+        }
+    }
+}
+
+/// Transcodes content with a byte order mark to UTF-8, without the byte order mark. Returns `None`
+/// when the content doesn't have to be decoded.
+pub fn decode(content: &[u8]) -> Option<(Vec<u8>, OffsetMap)> {
+    let decode_unit: fn([u8; 2]) -> u16 = match Encoding::detect(content) {
+        Encoding::Utf8 => return None,
+        Encoding::Utf8Bom => {
+            let mut offsets = OffsetMap::default();
+            offsets.push(0, UTF8_BOM.len(), 1, 1);
+            return Some((content[UTF8_BOM.len()..].to_vec(), offsets));
+        }
+        Encoding::Utf16Le => u16::from_le_bytes,
+        Encoding::Utf16Be => u16::from_be_bytes,
+    };
+
+    // A trailing odd byte is not part of any character
+    let units = content[UTF16_LE_BOM.len()..]
+        .chunks_exact(2)
+        .map(|unit| decode_unit([unit[0], unit[1]]));
+
+    let mut decoded = Vec::with_capacity(content.len() / 2);
+    let mut offsets = OffsetMap::default();
+    let mut original = UTF16_LE_BOM.len();
+
+    for c in char::decode_utf16(units) {
+        let (c, original_len) = match c {
+            Ok(c) => (c, c.len_utf16() * 2),
+            Err(_) => (char::REPLACEMENT_CHARACTER, 2),
+        };
+
+        offsets.push(decoded.len(), original, c.len_utf8(), original_len);
+        decoded.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        original += original_len;
+    }
+
+    Some((decoded, offsets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    fn utf16_le(content: &str) -> Vec<u8> {
+        content.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16_be(content: &str) -> Vec<u8> {
+        content.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn test_decode() {
+        let content = "<p class=\"flex\">Héllo 🔥</p> <b class=\"underline\">";
+
+        // Content without a byte order mark is left as-is
+        assert_eq!(decode(content.as_bytes()), None);
+
+        let utf8: fn(&str) -> Vec<u8> = |content| content.as_bytes().to_vec();
+        for (bom, encode) in [
+            (&b"\xEF\xBB\xBF"[..], utf8),
+            (&b"\xFF\xFE"[..], utf16_le),
+            (&b"\xFE\xFF"[..], utf16_be),
+        ] {
+            let original = [bom, &encode(content)].concat();
+            let (decoded, offsets) = decode(&original).unwrap();
+            assert_eq!(decoded, content.as_bytes());
+
+            for candidate in ["flex", "underline", "🔥"] {
+                let start = content.find(candidate).unwrap();
+                let range = offsets.original(start)..offsets.original(start + candidate.len());
+                assert_eq!(original[range.clone()], encode(candidate));
+                assert_eq!(offsets.decoded(range.start), start);
+                assert_eq!(offsets.decoded(range.end), start + candidate.len());
+            }
+
+            assert_eq!(offsets.original(decoded.len()), original.len());
+            assert_eq!(offsets.decoded(original.len()), decoded.len());
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use crate::scanner::auto_source_detection::AutoSourceDetection;
+use crate::scanner::limits::SkipReason;
+use crate::scanner::sources::SourceEntry;
+use crate::scanner::stats::PhaseCounters;
+use crate::scanner::{matches_source_pattern, read_file, Scanner};
+use crate::GlobEntry;
+use ignore::gitignore::Glob;
+use std::path::{Path, PathBuf};
+
+/// The final decision on whether a path is scanned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The file is scanned, or the folder is searched for files
+    Scanned,
+
+    /// The path, or one of its parent folders, is ignored by a rule
+    Ignored,
+
+    /// The file passes all ignore rules, but doesn't match any of the `@source` patterns
+    NotMatchingSource,
+
+    /// The file passes all rules, but is skipped because of its size or contents
+    Skipped,
+
+    /// The path is not inside any of the sources
+    OutsideSources,
+
+    /// The path doesn't exist
+    NotFound,
+}
+
+/// Where a rule that matched a path was defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleOrigin {
+    /// A `.gitignore` (or similar) file on disk, with the 1-based line number of the rule when it
+    /// could be found
+    IgnoreFile { file: PathBuf, line: Option<usize> },
+
+    /// An `@source` or `@source not` entry
+    Source,
+
+    /// One of the content folders we never scan, e.g.: `node_modules`
+    IgnoredContentDir,
+
+    /// One of the file extensions we never scan, e.g.: `.css`
+    IgnoredExtension,
+
+    /// One of the files we never scan, e.g.: `package-lock.json`
+    IgnoredFile,
+
+    /// One of the binary file extensions, e.g.: `.png`
+    BinaryExtension,
+}
+
+/// The rule that decided whether a path is ignored or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedRule {
+    pub origin: RuleOrigin,
+
+    /// The rule as it was written. For the built-in auto source detection rules, this is the part
+    /// of the rule that matched, e.g.: `*.png` instead of the full list of extensions.
+    pub pattern: String,
+
+    /// Whether the rule allows the path (`!pattern`) instead of ignoring it
+    pub whitelist: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub path: PathBuf,
+    pub verdict: Verdict,
+
+    /// The rule that decided the verdict, if any
+    pub rule: Option<MatchedRule>,
+
+    /// Why the file is skipped, only set when the verdict is `Skipped`
+    pub skip_reason: Option<SkipReason>,
+
+    /// All sources that include the path, ignoring `@source not` entries
+    pub sources: Vec<GlobEntry>,
+}
+
+impl Scanner {
+    /// Explain why a file (or folder) is, or isn't scanned.
+    ///
+    /// This applies the same rules as scanning does: the auto source detection rules, ignore
+    /// files, `@source` and `@source not` entries, the `@source` pattern check and the limits on
+    /// the size and contents of files.
+    #[tracing::instrument(skip(self))]
+    pub fn explain(&mut self, path: &Path) -> Explanation {
+        let Ok(canonical) = dunce::canonicalize(path) else {
+            return Explanation {
+                path: path.to_path_buf(),
+                verdict: Verdict::NotFound,
+                rule: None,
+                skip_reason: None,
+                sources: vec![],
+            };
+        };
+
+        let is_dir = canonical.is_dir();
+
+        // Prefer the canonical path, but a path reached through a symlink is only inside the
+        // sources under its original name.
+        let matched = match self.match_path(&canonical, is_dir) {
+            Some(matched) => Some((canonical.clone(), matched)),
+            None => std::path::absolute(path).ok().and_then(|path| {
+                let matched = self.match_path(&path, is_dir)?;
+                Some((path, matched))
+            }),
+        };
+
+        let Some((path, matched)) = matched else {
+            return Explanation {
+                sources: self.sources_of(&canonical),
+                path: canonical,
+                verdict: Verdict::OutsideSources,
+                rule: None,
+                skip_reason: None,
+            };
+        };
+
+        let rule = matched
+            .glob()
+            .map(|glob| explain_rule(glob, &path, &self.auto_source_detection));
+        let verdict = if matched.is_ignore() {
+            Verdict::Ignored
+        } else if !is_dir && !self.source_filter.matches(&path) {
+            Verdict::NotMatchingSource
+        } else {
+            Verdict::Scanned
+        };
+
+        // Files that pass all rules are still read, and can be skipped because of the limits
+        let skip_reason = match verdict {
+            Verdict::Scanned if !is_dir => {
+                read_file(&path, &self.limits, &PhaseCounters::default())
+                    .ok()
+                    .and_then(Result::err)
+            }
+            _ => None,
+        };
+
+        Explanation {
+            sources: self.sources_of(&path),
+            path,
+            verdict: match skip_reason {
+                Some(_) => Verdict::Skipped,
+                None => verdict,
+            },
+            rule,
+            skip_reason,
+        }
+    }
+
+    fn sources_of(&self, path: &Path) -> Vec<GlobEntry> {
+        self.sources
+            .iter()
+            .filter_map(|source| match source {
+                SourceEntry::Auto { base } | SourceEntry::External { base }
+                    if path.starts_with(base) =>
+                {
+                    Some(GlobEntry {
+                        base: base.to_string_lossy().to_string(),
+                        pattern: "**/*".to_string(),
+                    })
+                }
+                SourceEntry::Pattern { base, pattern }
+                    if matches_source_pattern(base, pattern, path) =>
+                {
+                    Some(GlobEntry {
+                        base: base.to_string_lossy().to_string(),
+                        pattern: pattern.to_string(),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn explain_rule(glob: &Glob, path: &Path, rules: &AutoSourceDetection) -> MatchedRule {
+    let whitelist = glob.is_whitelist();
+    let original = glob.original();
+
+    if let Some(file) = glob.from() {
+        return MatchedRule {
+            origin: RuleOrigin::IgnoreFile {
+                file: file.to_path_buf(),
+                line: line_of(file, original),
+            },
+            pattern: original.to_owned(),
+            whitelist,
+        };
+    }
+
+    let extension = || {
+        path.extension()
+            .map(|extension| format!("*.{}", extension.to_string_lossy()))
+    };
+
+    let is = |glob: &Option<String>| glob.as_deref() == Some(original);
+
+    let (origin, pattern) = if is(&rules.ignored_content_dirs_glob) {
+        let dir = path.ancestors().find_map(|ancestor| {
+            let name = ancestor.file_name()?.to_str()?;
+            rules
+                .ignored_content_dirs
+                .iter()
+                .any(|dir| dir == name)
+                .then(|| format!("{name}/"))
+        });
+        (RuleOrigin::IgnoredContentDir, dir)
+    } else if is(&rules.ignored_extensions_glob) {
+        (RuleOrigin::IgnoredExtension, extension())
+    } else if is(&rules.binary_extensions_glob) {
+        (RuleOrigin::BinaryExtension, extension())
+    } else if is(&rules.ignored_files_glob) {
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        (RuleOrigin::IgnoredFile, file)
+    } else {
+        (RuleOrigin::Source, None)
+    };
+
+    MatchedRule {
+        origin,
+        pattern: pattern.unwrap_or_else(|| original.to_owned()),
+        whitelist,
+    }
+}
+
+/// The 1-based line number of `rule` in the ignore `file`. When the same rule is defined more
+/// than once, the last one wins, just like it does when matching.
+fn line_of(file: &Path, rule: &str) -> Option<usize> {
+    let content = std::fs::read_to_string(file).ok()?;
+    let lines = content.lines().collect::<Vec<_>>();
+    lines
+        .iter()
+        .rposition(|line| line.trim_start_matches('\u{feff}').trim_end() == rule)
+        .map(|idx| idx + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RuleOrigin, Verdict};
+    use crate::scanner::limits::SkipReason;
+    use crate::scanner::sources::PublicSourceEntry;
+    use crate::{Scanner, ScannerOptions};
+    use std::fs;
+
+    #[test]
+    fn test_explain() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dunce::canonicalize(dir.path()).unwrap();
+
+        for (path, content) in [
+            (
+                ".gitignore",
+                "# Build output\ndist/\ngenerated/\n!generated/\ngenerated/\n",
+            ),
+            ("src/index.html", "flex"),
+            ("src/data.html", "flex\0\0\0"),
+            ("src/generated/index.html", "flex"),
+            ("src/logo.png", ""),
+            ("src/index.md", ""),
+            ("dist/index.html", "flex"),
+            ("node_modules/pkg/index.js", "flex"),
+            ("src/node_modules/pkg/index.js", "flex"),
+        ] {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut scanner = Scanner::new(vec![
+            PublicSourceEntry {
+                base: dir.join("src").to_string_lossy().to_string(),
+                pattern: "**/*".to_string(),
+                negated: false,
+            },
+            PublicSourceEntry {
+                base: dir.join("src").to_string_lossy().to_string(),
+                pattern: "**/*.md".to_string(),
+                negated: true,
+            },
+            PublicSourceEntry {
+                base: dir.to_string_lossy().to_string(),
+                pattern: "dist/*.html".to_string(),
+                negated: false,
+            },
+        ]);
+
+        let explanation = scanner.explain(&dir.join("src/index.html"));
+        assert_eq!(explanation.verdict, Verdict::Scanned);
+        assert_eq!(explanation.rule, None);
+        assert_eq!(explanation.sources.len(), 1);
+
+        let explanation = scanner.explain(&dir.join("src/logo.png"));
+        assert_eq!(explanation.verdict, Verdict::Ignored);
+        let rule = explanation.rule.unwrap();
+        assert_eq!(rule.origin, RuleOrigin::BinaryExtension);
+        assert_eq!(rule.pattern, "*.png");
+
+        let explanation = scanner.explain(&dir.join("src/index.md"));
+        assert_eq!(explanation.verdict, Verdict::Ignored);
+        let rule = explanation.rule.unwrap();
+        assert_eq!(rule.origin, RuleOrigin::Source);
+        assert_eq!(rule.pattern, "/**/*.md");
+
+        // Explicit `@source` patterns take precedence over ignore files
+        let explanation = scanner.explain(&dir.join("dist/index.html"));
+        assert_eq!(explanation.verdict, Verdict::Scanned);
+        let rule = explanation.rule.unwrap();
+        assert_eq!(rule.origin, RuleOrigin::Source);
+        assert!(rule.whitelist);
+
+        let explanation = scanner.explain(&dir.join("src/node_modules/pkg/index.js"));
+        assert_eq!(explanation.verdict, Verdict::Ignored);
+        let rule = explanation.rule.unwrap();
+        assert_eq!(rule.origin, RuleOrigin::IgnoredContentDir);
+        assert_eq!(rule.pattern, "node_modules/");
+
+        let explanation = scanner.explain(&dir.join("node_modules/pkg/index.js"));
+        assert_eq!(explanation.verdict, Verdict::OutsideSources);
+
+        let explanation = scanner.explain(&dir.join("src/missing.html"));
+        assert_eq!(explanation.verdict, Verdict::NotFound);
+
+        let explanation = scanner.explain(&dir.join("src/generated/index.html"));
+        assert_eq!(explanation.verdict, Verdict::Ignored);
+        let rule = explanation.rule.unwrap();
+        assert_eq!(
+            rule.origin,
+            RuleOrigin::IgnoreFile {
+                file: dir.join(".gitignore"),
+                line: Some(5),
+            }
+        );
+        assert_eq!(rule.pattern, "generated/");
+
+        // Files that pass all rules can still be skipped because of their contents, or size
+        let explanation = scanner.explain(&dir.join("src/data.html"));
+        assert_eq!(explanation.verdict, Verdict::Skipped);
+        assert_eq!(explanation.skip_reason, Some(SkipReason::Binary));
+
+        let mut scanner = Scanner::new_with_options(
+            vec![PublicSourceEntry {
+                base: dir.join("src").to_string_lossy().to_string(),
+                pattern: "**/*".to_string(),
+                negated: false,
+            }],
+            ScannerOptions {
+                max_file_size: Some(2),
+                ..Default::default()
+            },
+        );
+
+        let explanation = scanner.explain(&dir.join("src/index.html"));
+        assert_eq!(explanation.verdict, Verdict::Skipped);
+        assert_eq!(
+            explanation.skip_reason,
+            Some(SkipReason::TooLarge {
+                size: 4,
+                max_file_size: 2
+            })
+        );
+    }
+}
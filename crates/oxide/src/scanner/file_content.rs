@@ -0,0 +1,161 @@
+use crate::scanner::cache::FileStamp;
+use crate::scanner::encoding::{self, OffsetMap};
+use std::ops::Deref;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Files of at least this size are memory mapped instead of read into memory.
+#[cfg(not(target_family = "wasm"))]
+pub const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// The content of a file, or of raw content.
+///
+/// Large files can be memory mapped, such that they can be extracted without copying them into
+/// memory first, see `read_mapped`. Small files are always read into memory, because mapping them
+/// is more expensive than reading them.
+///
+/// Files with a byte order mark are transcoded to UTF-8, offsets in the transcoded content can be
+/// mapped back to the file with `original_offset`. The stamp of the bytes on disk is kept, see
+/// `stamp`.
+#[derive(Debug)]
+pub enum FileContent {
+    Buffered(Vec<u8>),
+
+    #[cfg(not(target_family = "wasm"))]
+    Mapped(memmap2::Mmap),
+
+    Decoded(Vec<u8>, OffsetMap, FileStamp),
+}
+
+impl FileContent {
+    /// Read the file into memory. This is safe for files that can change at any time, e.g.: files
+    /// that are being edited while watching.
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::decode(std::fs::read(path).map(Self::Buffered)?))
+    }
+
+    /// Like `read`, but memory maps large files.
+    ///
+    /// Only use this for files that are not expected to change while they are mapped. When a
+    /// mapped file is truncated, reading the part that no longer exists raises `SIGBUS` and
+    /// kills the whole process. Editors and bundlers rewrite files all the time while watching,
+    /// so this is only used for the initial scan.
+    pub fn read_mapped(path: &Path) -> std::io::Result<Self> {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let file = std::fs::File::open(path)?;
+            if file.metadata()?.len() >= MMAP_THRESHOLD {
+                // SAFETY: The mapping is only read from, and is dropped as soon as the file is
+                // extracted. The caller guarantees the file is not expected to be truncated in the
+                // meantime, which would raise `SIGBUS` (see above).
+                if let Ok(mmap) = unsafe { memmap2::Mmap::map(&file) } {
+                    return Ok(Self::decode(Self::Mapped(mmap)));
+                }
+            }
+        }
+
+        Self::read(path)
+    }
+
+    fn decode(content: Self) -> Self {
+        match encoding::decode(&content) {
+            Some((decoded, offsets)) => {
+                let stamp = FileStamp::new(None, &content);
+                Self::Decoded(decoded, offsets, stamp)
+            }
+            None => content,
+        }
+    }
+
+    /// Replace the content, while keeping the offsets intact. This is used for pre-processed
+    /// content.
+    pub fn with_content(self, content: Vec<u8>) -> Self {
+        match self {
+            Self::Decoded(_, offsets, stamp) => Self::Decoded(content, offsets, stamp),
+            _ => Self::Buffered(content),
+        }
+    }
+
+    /// The byte offset in the file, for a byte offset in the content.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        match self {
+            Self::Decoded(_, offsets, _) => offsets.original(offset),
+            _ => offset,
+        }
+    }
+
+    /// The byte offset in the content, for a byte offset in the file.
+    pub fn decoded_offset(&self, offset: usize) -> usize {
+        match self {
+            Self::Decoded(_, offsets, _) => offsets.decoded(offset),
+            _ => offset,
+        }
+    }
+
+    /// The stamp of the file as it is on disk. For transcoded files, this is the stamp of the
+    /// bytes before they were transcoded, such that it can be compared with the size of the file.
+    pub fn stamp(&self, mtime: Option<SystemTime>) -> FileStamp {
+        match self {
+            Self::Decoded(_, _, stamp) => FileStamp { mtime, ..*stamp },
+            content => FileStamp::new(mtime, content),
+        }
+    }
+}
+
+impl Deref for FileContent {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Buffered(content) => content,
+
+            #[cfg(not(target_family = "wasm"))]
+            Self::Mapped(mmap) => mmap,
+
+            Self::Decoded(content, _, _) => content,
+        }
+    }
+}
+
+impl Default for FileContent {
+    fn default() -> Self {
+        Self::Buffered(vec![])
+    }
+}
+
+#[cfg(all(test, not(target_family = "wasm")))]
+mod tests {
+    use super::{FileContent, MMAP_THRESHOLD};
+    use std::fs;
+
+    #[test]
+    fn test_read_small_and_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let small = dir.path().join("small.html");
+        fs::write(&small, "flex").unwrap();
+        let content = FileContent::read(&small).unwrap();
+        assert!(matches!(content, FileContent::Buffered(_)));
+        assert_eq!(&*content, b"flex");
+
+        let large = dir.path().join("large.json");
+        let data = "[\"flex\"]\n".repeat(MMAP_THRESHOLD as usize / 9 + 1);
+        fs::write(&large, &data).unwrap();
+        let content = FileContent::read_mapped(&large).unwrap();
+        assert!(matches!(content, FileContent::Mapped(_)));
+        assert_eq!(&*content, data.as_bytes());
+
+        // Files that can change at any time are never mapped
+        let content = FileContent::read(&large).unwrap();
+        assert!(matches!(content, FileContent::Buffered(_)));
+        assert_eq!(&*content, data.as_bytes());
+
+        let utf16 = dir.path().join("utf16.cshtml");
+        let data = [&[0xFF, 0xFE][..], &[b'f', 0, b'l', 0, b'e', 0, b'x', 0]].concat();
+        fs::write(&utf16, data).unwrap();
+        let content = FileContent::read(&utf16).unwrap();
+        assert_eq!(&*content, b"flex");
+        assert_eq!(content.original_offset(4), 10);
+        assert_eq!(content.stamp(None).size, 10);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+/// Only the start of a file is used to decide whether it is binary.
+const BINARY_SAMPLE_SIZE: usize = 8 * 1024;
+
+/// Only the start of a file is used to decide whether it is minified, such that large files don't
+/// have to be read in full.
+const MINIFIED_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Files smaller than this are never considered minified, a single long line of markup is common
+/// and harmless.
+const MINIFIED_MIN_SIZE: usize = 4 * 1024;
+
+/// Hand written code rarely averages more than a hundred bytes per line, minified code is usually
+/// a handful of very long lines.
+const MINIFIED_AVERAGE_LINE_LENGTH: usize = 500;
+
+/// Rules for files that are skipped instead of scanned, e.g.: a 40 MB `vendor.min.js` bundle.
+///
+/// Files that look binary are always skipped, regardless of their extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentLimits {
+    /// Files larger than this many bytes are skipped
+    pub max_file_size: Option<u64>,
+
+    /// Whether files that look minified are skipped
+    pub skip_minified: bool,
+}
+
+/// Why a file was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    TooLarge { size: u64, max_file_size: u64 },
+    Minified { average_line_length: usize },
+    Binary,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge {
+                size,
+                max_file_size,
+            } => write!(
+                f,
+                "file size of {size} bytes exceeds the maximum of {max_file_size} bytes"
+            ),
+            Self::Minified {
+                average_line_length,
+            } => write!(
+                f,
+                "looks minified, with an average line length of {average_line_length} bytes"
+            ),
+            Self::Binary => write!(f, "looks like a binary file"),
+        }
+    }
+}
+
+impl ContentLimits {
+    /// Checks the size of a file, before it is read.
+    pub fn check_size(&self, size: u64) -> Option<SkipReason> {
+        let max_file_size = self.max_file_size?;
+        (size > max_file_size).then_some(SkipReason::TooLarge {
+            size,
+            max_file_size,
+        })
+    }
+
+    /// Checks the contents of a file, after it is read and transcoded to UTF-8.
+    pub fn check_content(&self, content: &[u8]) -> Option<SkipReason> {
+        if is_binary(content) {
+            return Some(SkipReason::Binary);
+        }
+
+        if !self.skip_minified || content.len() < MINIFIED_MIN_SIZE {
+            return None;
+        }
+
+        let sample = &content[..content.len().min(MINIFIED_SAMPLE_SIZE)];
+        let lines = sample.iter().filter(|byte| **byte == b'\n').count() + 1;
+        let average_line_length = sample.len() / lines;
+
+        (average_line_length > MINIFIED_AVERAGE_LINE_LENGTH).then_some(SkipReason::Minified {
+            average_line_length,
+        })
+    }
+}
+
+/// Whether the content looks binary: it contains a NUL byte, or a large part of it is not valid
+/// UTF-8.
+fn is_binary(content: &[u8]) -> bool {
+    let sample = &content[..content.len().min(BINARY_SAMPLE_SIZE)];
+    if sample.contains(&0) {
+        return true;
+    }
+
+    let invalid = sample
+        .utf8_chunks()
+        .map(|chunk| chunk.invalid().len())
+        .sum::<usize>();
+
+    invalid > sample.len() / 8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContentLimits, SkipReason};
+    use crate::scanner::encoding::decode;
+
+    #[test]
+    fn test_content_limits() {
+        let limits = ContentLimits {
+            max_file_size: Some(1024),
+            skip_minified: true,
+        };
+
+        assert_eq!(limits.check_size(1024), None);
+        assert_eq!(
+            limits.check_size(1025),
+            Some(SkipReason::TooLarge {
+                size: 1025,
+                max_file_size: 1024
+            })
+        );
+
+        let source = "<div class=\"flex p-4\">Hello</div>\n".repeat(500);
+        assert_eq!(limits.check_content(source.as_bytes()), None);
+
+        let minified = "var a=\"flex p-4\";".repeat(500);
+        assert_eq!(
+            limits.check_content(minified.as_bytes()),
+            Some(SkipReason::Minified {
+                average_line_length: minified.len()
+            })
+        );
+
+        // A single long line in a small file is fine
+        assert_eq!(limits.check_content("flex ".repeat(200).as_bytes()), None);
+
+        // Nothing is skipped by default
+        let limits = ContentLimits::default();
+        assert_eq!(limits.check_size(u64::MAX), None);
+        assert_eq!(limits.check_content(minified.as_bytes()), None);
+    }
+
+    #[test]
+    fn test_binary_content() {
+        let limits = ContentLimits::default();
+        let binary = Some(SkipReason::Binary);
+
+        assert_eq!(limits.check_content(b"\0asm\x01\0\0\0"), binary);
+        assert_eq!(limits.check_content(b"Bud1\x80\x81\xfe\xff"), binary);
+        assert_eq!(limits.check_content(&[0xFF; 64]), binary);
+
+        // A few invalid bytes in text are fine
+        assert_eq!(limits.check_content(b"flex bg-[\x80] block"), None);
+        assert_eq!(limits.check_content("<p>Héllo 🔥</p>".as_bytes()), None);
+
+        // UTF-16 text contains NUL bytes, but it is transcoded before it is checked
+        let utf16 = b"\xFF\xFEf\0l\0e\0x\0";
+        assert_eq!(limits.check_content(utf16), binary);
+        let (decoded, _) = decode(utf16).unwrap();
+        assert_eq!(limits.check_content(&decoded), None);
+    }
+}
@@ -1,25 +1,48 @@
 pub mod auto_source_detection;
+pub mod cache;
+pub mod candidate_index;
 pub mod detect_sources;
+pub mod encoding;
+pub mod explain;
+pub mod file_content;
 pub mod init_tracing;
+pub mod limits;
+pub mod pre_processing;
 pub mod sources;
-
-use crate::extractor::{Extracted, Extractor};
+pub mod stats;
+pub mod usage;
+#[cfg(not(target_family = "wasm"))]
+pub mod watcher;
+
+use crate::extractor::allowlist::CandidateAllowlist;
+use crate::extractor::pre_processors::{
+    process_checking_offsets, BuiltinPreProcessor, PreProcessor,
+};
+use crate::extractor::{Extracted, ExtractedKind, Extractor, ExtractorOptions};
 use crate::glob::optimize_patterns;
+use crate::scanner::cache::{hash_content, FileStamp, ScanCache};
+use crate::scanner::candidate_index::{CandidateChanges, CandidateIndex};
 use crate::scanner::detect_sources::resolve_globs;
+use crate::scanner::file_content::FileContent;
+use crate::scanner::limits::{ContentLimits, SkipReason};
 use crate::scanner::sources::{
     public_source_entries_to_private_source_entries, PublicSourceEntry, SourceEntry, Sources,
 };
+use crate::scanner::stats::{PhaseCounters, ScanStats};
+use crate::scanner::usage::{ContentUsage, UsageIndex, UsageReport};
 use crate::GlobEntry;
-use auto_source_detection::BINARY_EXTENSIONS_GLOB;
+use auto_source_detection::{AutoSourceDetection, AutoSourceDetectionOptions};
 use bstr::ByteSlice;
 use fast_glob::glob_match;
 use fxhash::{FxHashMap, FxHashSet};
-use ignore::{gitignore::GitignoreBuilder, WalkBuilder};
+use ignore::{gitignore::GitignoreBuilder, IncrementalIgnore, IncrementalMatch, WalkBuilder};
 use init_tracing::{init_tracing, SHOULD_TRACE};
+use pre_processing::{PreProcessorMapping, PreProcessorRegistry};
 use rayon::prelude::*;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::event;
 
 // @source "some/folder";               // This is auto source detection
@@ -37,8 +60,80 @@ use tracing::event;
 pub enum ChangedContent {
     File(PathBuf, String),
     Content(String, String),
+
+    /// A file, or a folder with all its files, that no longer exists
+    Removed(PathBuf),
+
+    /// A file that was renamed from the first path to the second path, with the extension of the
+    /// new path
+    Renamed(PathBuf, PathBuf, String),
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct ScannerOptions {
+    /// Directory to store the persistent scan cache in, e.g.: `.tailwindcss/cache`. This allows a
+    /// new process to skip reading and extracting files that didn't change since the last run.
+    ///
+    /// The cache is disabled when not set.
+    pub cache_dir: Option<PathBuf>,
+
+    /// How incremental scans decide whether a file changed
+    pub change_detection: ChangeDetection,
+
+    /// Changes to the built-in auto source detection rules
+    pub auto_source_detection: AutoSourceDetectionOptions,
+
+    /// Route extensions or globs to a built-in pre-processor, e.g.: `.view` files to the `Vue`
+    /// pre-processor
+    pub pre_processors: Vec<PreProcessorMapping>,
+
+    /// Files larger than this many bytes are not scanned
+    pub max_file_size: Option<u64>,
+
+    /// Skip files that look minified, e.g.: a `vendor.min.js` bundle. A file looks minified when
+    /// its lines are very long on average.
+    pub skip_minified_files: bool,
+
+    /// The prefix of the design system, e.g.: `tw`. When set, only candidates that start with the
+    /// prefix as their first variant (`tw:flex`) and CSS variables are extracted.
+    pub prefix: Option<String>,
+
+    /// The utility roots and variant names of the design system. When set, candidates that can
+    /// never match are dropped during extraction, e.g.: `class` or `focus:flex` without a `focus`
+    /// variant.
+    pub allowlist: Option<CandidateAllowlist>,
+
+    /// Track how often every candidate occurs, see `Scanner::get_usage_report`. The persistent
+    /// cache doesn't store these counts, so it is not used when this is enabled.
+    pub usage_statistics: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChangeDetection {
+    /// A file changed when its mtime changed. This is the fastest option, but it can miss edits
+    /// on file systems with a coarse mtime resolution, and rescans files that are only touched.
+    #[default]
+    Mtime,
+
+    /// A file changed when its size or the hash of its contents changed. The mtime and size are
+    /// only used as a pre-filter, to avoid reading files that were not touched since the last
+    /// scan.
+    ContentHash,
+}
+
+/// Files modified this close to the start of the previous scan could have been modified during
+/// that scan without changing their mtime, on file systems with a coarse mtime resolution. Their
+/// mtime can't be trusted, so their contents are compared instead.
+const RACY_MTIME_WINDOW: Duration = Duration::from_secs(2);
+
+/// How many batches of walked entries, and how many files waiting to be read, can be queued
+/// between the stages of a scan. This bounds the memory used by a scan, regardless of the size of
+/// the project.
+const PIPELINE_CAPACITY: usize = 64;
+
+/// How many walked entries are sent to the next stage at once
+const WALK_BATCH_SIZE: usize = 256;
+
 #[derive(Debug, Clone)]
 pub struct ScanOptions {
     /// Base path to start scanning from
@@ -48,6 +143,90 @@ pub struct ScanOptions {
     pub sources: Vec<GlobEntry>,
 }
 
+/// A single occurrence of a candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidatePosition {
+    /// The file the candidate was found in
+    pub file: PathBuf,
+
+    /// The candidate, or the CSS variable
+    pub candidate: String,
+
+    pub kind: ExtractedKind,
+
+    /// Byte range of the candidate inside the file
+    pub range: Range<usize>,
+
+    /// 1-based line number
+    pub line: usize,
+
+    /// 0-based byte offset from the start of the line
+    pub column: usize,
+}
+
+/// All the places a candidate was found in a single file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateLocation {
+    /// The file the candidate was found in
+    pub file: PathBuf,
+
+    /// Byte offsets of every occurrence of the candidate inside the file
+    pub offsets: Vec<usize>,
+}
+
+/// What changed during a single `scan_diff()` or `scan_content_diff()` call.
+///
+/// Files are reported by their canonical path, with symlinks resolved. The same file is reported
+/// under the same path, regardless of how it was found or which call reported it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanDiff {
+    /// Candidates that weren't known before
+    pub added: Vec<String>,
+
+    /// Candidates that are no longer used by any file
+    pub removed: Vec<String>,
+
+    /// Files that were (re-)scanned because they are new or have changed
+    pub changed_files: Vec<String>,
+
+    /// Files that were scanned before, but no longer exist or are no longer part of the sources
+    pub deleted_files: Vec<String>,
+}
+
+impl ScanDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed_files.is_empty()
+            && self.deleted_files.is_empty()
+    }
+
+    fn new(
+        changes: CandidateChanges,
+        changed_files: Vec<String>,
+        deleted_files: Vec<PathBuf>,
+    ) -> Self {
+        let mut added: Vec<String> = changes.added.into_iter().collect();
+        added.par_sort_unstable();
+
+        let mut removed: Vec<String> = changes.removed.into_iter().collect();
+        removed.par_sort_unstable();
+
+        let mut deleted_files: Vec<String> = deleted_files
+            .into_iter()
+            .filter_map(|file| file.into_os_string().into_string().ok())
+            .collect();
+        deleted_files.par_sort_unstable();
+
+        Self {
+            added,
+            removed,
+            changed_files,
+            deleted_files,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ScanResult {
     pub candidates: Vec<String>,
@@ -63,6 +242,14 @@ pub struct Scanner {
     /// The walker to detect all files that we have to scan
     walker: Option<WalkBuilder>,
 
+    /// Ignore matchers for every root of the walker, used to check individual files without
+    /// walking the file system. Built on first use, and reset on every full scan so changes to
+    /// ignore files are picked up.
+    matchers: Option<Vec<IncrementalIgnore>>,
+
+    /// Verifies that files match the `@source` patterns, next to the ignore rules of the walker
+    source_filter: SourceFilter,
+
     /// All found extensions
     extensions: FxHashSet<String>,
 
@@ -75,8 +262,8 @@ pub struct Scanner {
     /// All generated globs, used for setting up watchers
     globs: Option<Vec<GlobEntry>>,
 
-    /// Track unique set of candidates
-    candidates: FxHashSet<String>,
+    /// Track unique set of candidates, and the files they were found in
+    candidates: CandidateIndex,
 
     /// Track mtimes for files so incremental scans can skip unchanged files.
     mtimes: FxHashMap<PathBuf, SystemTime>,
@@ -84,16 +271,54 @@ pub struct Scanner {
     /// Files that were scanned during the last `scan()` call.
     scanned_files: Vec<String>,
 
+    /// Candidates that were removed by the last scan, the `removed` part of its `ScanDiff`
+    removed_candidates: Vec<String>,
+
     /// Whether we've completed at least one full scan. When false, we skip
     /// mtime tracking entirely so the initial build stays fast.
     has_scanned_once: bool,
 
     /// Whether sources have been scanned since the last `scan()` call
     sources_scanned: bool,
+
+    /// Location of the persistent scan cache, only set when the cache is enabled
+    cache_file: Option<PathBuf>,
+
+    /// Size and content hash of every scanned file, only tracked when the cache or content hash
+    /// based change detection is enabled
+    stamps: FxHashMap<PathBuf, FileStamp>,
+
+    /// How incremental scans decide whether a file changed
+    change_detection: ChangeDetection,
+
+    /// The auto source detection rules, including the changes from the options
+    auto_source_detection: AutoSourceDetection,
+
+    /// When the file system was last walked, used to detect mtimes that can't be trusted
+    last_walk: Option<SystemTime>,
+
+    /// The pre-processors for each extension or glob
+    pre_processors: PreProcessorRegistry,
+
+    /// Which files are skipped instead of scanned
+    limits: ContentLimits,
+
+    /// Which candidates are extracted
+    extractor_options: ExtractorOptions,
+
+    /// Numbers about the last scan
+    stats: ScanStats,
+
+    /// How often candidates occur in every file, only tracked when usage statistics are enabled
+    usage: Option<UsageIndex>,
 }
 
 impl Scanner {
     pub fn new(sources: Vec<PublicSourceEntry>) -> Self {
+        Self::new_with_options(sources, ScannerOptions::default())
+    }
+
+    pub fn new_with_options(sources: Vec<PublicSourceEntry>, options: ScannerOptions) -> Self {
         init_tracing();
 
         if *SHOULD_TRACE {
@@ -103,7 +328,11 @@ impl Scanner {
             }
         }
 
-        let sources = Sources::new(public_source_entries_to_private_source_entries(sources));
+        let auto_source_detection = AutoSourceDetection::new(&options.auto_source_detection);
+        let sources = Sources::new(public_source_entries_to_private_source_entries(
+            sources,
+            &auto_source_detection.ignored_content_dirs,
+        ));
         if *SHOULD_TRACE {
             event!(tracing::Level::INFO, "Optimized sources:");
             for source in sources.iter() {
@@ -111,38 +340,142 @@ impl Scanner {
             }
         }
 
-        let walker = create_walker(&sources);
+        let walker = create_walker(&sources, &auto_source_detection);
+        let source_filter = SourceFilter::new(&sources);
+
+        let cache_file = options
+            .cache_dir
+            .map(|dir| ScanCache::file_for(&dir, &sources));
 
         Self {
             sources,
             walker,
+            source_filter,
+            cache_file,
+            change_detection: options.change_detection,
+            auto_source_detection,
+            pre_processors: PreProcessorRegistry::new(&options.pre_processors),
+            limits: ContentLimits {
+                max_file_size: options.max_file_size,
+                skip_minified: options.skip_minified_files,
+            },
+            extractor_options: ExtractorOptions {
+                prefix: options.prefix,
+                allowlist: options.allowlist,
+            },
+            usage: options.usage_statistics.then(UsageIndex::default),
             ..Default::default()
         }
     }
 
-    pub fn scan(&mut self) -> Vec<String> {
-        self.sources_scanned = false;
+    /// Use `pre_processor` for all files with the given extension (`blade.php`) or glob
+    /// (`*.liquid.html`), instead of the built-in pre-processor.
+    ///
+    /// Files that were scanned already are only pre-processed again once they change. Languages
+    /// embedded in other files, like the template of a `.vue` file, keep using the built-in
+    /// pre-processors.
+    pub fn register_pre_processor(&mut self, pattern: &str, pre_processor: Box<dyn PreProcessor>) {
+        self.pre_processors.register(pattern, pre_processor);
+    }
 
-        let (scanned_blobs, css_files, files) = self.discover_sources();
+    /// Stop pre-processing files with the given extension or glob, this includes the built-in
+    /// pre-processors.
+    pub fn unregister_pre_processor(&mut self, pattern: &str) {
+        self.pre_processors.unregister(pattern);
+    }
 
-        self.extract_candidates(scanned_blobs, css_files);
-        self.scanned_files = files;
+    pub fn scan(&mut self) -> Vec<String> {
+        self.scan_diff();
 
         // Return all candidates sorted
-        let mut result = self.candidates.iter().cloned().collect::<Vec<_>>();
+        let mut result = self.candidates.candidates().cloned().collect::<Vec<_>>();
         result.par_sort_unstable();
         result
     }
 
-    #[tracing::instrument(skip_all)]
+    /// Scan all sources, and report what changed compared to the previous scan instead of
+    /// returning all known candidates.
+    pub fn scan_diff(&mut self) -> ScanDiff {
+        self.sources_scanned = false;
+        self.matchers = None;
+
+        let discovered = self.discover_sources();
+
+        // Files that disappeared since the last scan no longer contribute any candidates
+        let mut changes = CandidateChanges::default();
+        let known_files = &self.files;
+        let deleted_files = self
+            .candidates
+            .retain_files(|file| known_files.contains(file), &mut changes);
+
+        for (file, candidates) in discovered.candidates {
+            self.candidates.update_file(file, candidates, &mut changes);
+        }
+
+        if let Some(usage) = &mut self.usage {
+            usage.retain_files(|file| known_files.contains(file));
+            for (file, file_usage) in discovered.usage {
+                usage.update_file(file, file_usage);
+            }
+        }
+
+        self.scanned_files = discovered.changed_files;
+        self.stats = discovered.stats;
+
+        let diff = ScanDiff::new(changes, discovered.changed_tracked_files, deleted_files);
+        self.removed_candidates = diff.removed.clone();
+
+        let known_files = &self.files;
+        self.stamps.retain(|file, _| known_files.contains(file));
+
+        if let Some((cache_file, options)) = self.cache_file.as_ref().zip(self.cache_key()) {
+            if !diff.is_empty() {
+                if let Err(err) =
+                    ScanCache::save(cache_file, options, &self.candidates, &self.stamps)
+                {
+                    event!(
+                        tracing::Level::WARN,
+                        "Failed to write scan cache {:?}: {}",
+                        cache_file,
+                        err
+                    );
+                }
+            }
+        }
+
+        diff
+    }
+
     pub fn scan_content(&mut self, changed_content: Vec<ChangedContent>) -> Vec<String> {
-        let (changed_files, changed_contents) =
-            changed_content
-                .into_iter()
-                .partition::<Vec<_>, _>(|x| match x {
-                    ChangedContent::File(_, _) => true,
-                    ChangedContent::Content(_, _) => false,
-                });
+        self.scan_content_diff(changed_content).added
+    }
+
+    /// Scan the changed content, and report what changed instead of only returning the new
+    /// candidates.
+    #[tracing::instrument(skip_all)]
+    pub fn scan_content_diff(&mut self, changed_content: Vec<ChangedContent>) -> ScanDiff {
+        let started = Instant::now();
+        let counters = PhaseCounters::default();
+
+        // A renamed file is a removed file, and a new file at the same time
+        let mut removed_files = vec![];
+        let (changed_files, changed_contents) = changed_content
+            .into_iter()
+            .filter_map(|changed_content| match changed_content {
+                ChangedContent::Removed(file) => {
+                    removed_files.push(file);
+                    None
+                }
+                ChangedContent::Renamed(from, to, extension) => {
+                    removed_files.push(from);
+                    Some(ChangedContent::File(to, extension))
+                }
+                changed_content => Some(changed_content),
+            })
+            .partition::<Vec<_>, _>(|x| matches!(x, ChangedContent::File(_, _)));
+
+        let mut changes = CandidateChanges::default();
+        let deleted_files = self.remove_files(removed_files, &mut changes);
 
         // Raw content can be parsed directly, no need to verify if the file exists and is allowed
         // to be scanned.
@@ -165,21 +498,43 @@ impl Scanner {
         let (known_files, mut new_unknown_files) = changed_files
             .into_iter()
             .partition::<Vec<_>, _>(|changed_file| match changed_file {
-                ChangedContent::Content(_, _) => unreachable!(),
                 ChangedContent::File(file, _) => self.files.contains(file),
+                _ => unreachable!(),
             });
 
         // All known files are allowed to be scanned
         content_to_scan.extend(known_files);
 
-        // Figure out if the new unknown files are allowed to be scanned
+        // Figure out if the new unknown files are allowed to be scanned. Checking the ignore rules
+        // of each file only costs a few directory lookups, but files that don't live inside any
+        // of the roots (e.g.: reached through a symlink) can only be found by walking.
+        new_unknown_files.retain(|changed_file| {
+            let ChangedContent::File(file, _) = changed_file else {
+                return false;
+            };
+
+            match self.is_allowed(file) {
+                Some(true) => {
+                    self.files.insert(file.clone()); // Track for future use
+                    content_to_scan.push(changed_file.clone()); // Track for parsing
+                    false
+                }
+                Some(false) => false,
+                None => true,
+            }
+        });
+
         if !new_unknown_files.is_empty() {
             if let Some(walk_builder) = &mut self.walker {
+                let walk_started = Instant::now();
                 for entry in walk_builder.build().filter_map(Result::ok) {
                     let path = entry.path();
                     if !path.is_file() {
                         continue;
                     }
+                    counters
+                        .walking
+                        .add_file(entry.metadata().map_or(0, |metadata| metadata.len()));
 
                     // The walked path can contain symlinks, while the changed files have already
                     // been canonicalized. Lazily canonicalize the walked path so we can compare
@@ -222,41 +577,162 @@ impl Scanner {
                         break;
                     }
                 }
+                counters.walking.add_time(walk_started);
+            }
+        }
+
+        let mut changed_files = content_to_scan
+            .iter()
+            .filter_map(|changed_content| match changed_content {
+                ChangedContent::File(file, _) => file.clone().into_os_string().into_string().ok(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        changed_files.par_sort_unstable();
+
+        let track_stamps = self.tracks_stamps();
+
+        let mut stats = ScanStats::default();
+        for (file, stamp, candidates) in extract_all_content(
+            content_to_scan,
+            &self.pre_processors,
+            &self.limits,
+            &self.extractor_options,
+            self.usage.is_some(),
+            &counters,
+            track_stamps,
+        ) {
+            // Keep the stamps in sync, such that the persistent cache and content hash based
+            // change detection know about the new contents
+            if let Some(file) = &file {
+                match stamp {
+                    Some(stamp) => self.stamps.insert(file.clone(), stamp),
+                    None => self.stamps.remove(file),
+                };
+            }
+
+            let (candidates, content_usage) = candidates.unwrap_or_else(|reason| {
+                if let Some(file) = &file {
+                    stats.record_skipped(file, reason);
+                }
+
+                (FxHashSet::default(), None)
+            });
+
+            match (&mut self.usage, &file, content_usage) {
+                (Some(usage), Some(file), content_usage) => {
+                    usage.update_file(file.clone(), content_usage)
+                }
+                (Some(usage), None, Some(content_usage)) => usage.add_content(content_usage),
+                _ => {}
+            }
+
+            match file {
+                Some(file) => self.candidates.update_file(file, candidates, &mut changes),
+                None => self.candidates.add_content(candidates, &mut changes),
             }
         }
 
-        // Read all content into blobs for extraction
-        let blobs = read_all_files(content_to_scan);
-        self.extract_candidates(blobs, vec![])
+        stats.finish(started, &counters);
+        self.stats = stats;
+
+        let diff = ScanDiff::new(changes, changed_files, deleted_files);
+        self.removed_candidates = diff.removed.clone();
+        diff
     }
 
-    #[tracing::instrument(skip_all)]
-    fn extract_candidates(&mut self, blobs: Vec<Vec<u8>>, css_files: Vec<PathBuf>) -> Vec<String> {
-        // Extract all candidates from the pre-read blobs
-        let mut new_candidates = parse_all_blobs(blobs);
-
-        // Extract all CSS variables from the CSS files
-        if !css_files.is_empty() {
-            let css_variables = extract_css_variables(read_all_files(
-                css_files
-                    .into_iter()
-                    .map(|file| ChangedContent::File(file, "css".into()))
-                    .collect(),
-            ));
+    /// Whether the size and content hash of every scanned file is tracked
+    fn tracks_stamps(&self) -> bool {
+        self.cache_file.is_some() || self.change_detection == ChangeDetection::ContentHash
+    }
 
-            new_candidates.extend(css_variables);
+    /// Hash of every option that changes which candidates are extracted from a file, used to
+    /// invalidate the persistent cache. Returns `None` when a custom pre-processor without a
+    /// `cache_key` is registered, the cache is not used in that case.
+    fn cache_key(&self) -> Option<u64> {
+        let key = format!(
+            "{:?}\n{:?}\n{}\n{}",
+            self.limits,
+            self.extractor_options.prefix,
+            self.extractor_options
+                .allowlist
+                .as_ref()
+                .map(CandidateAllowlist::cache_key)
+                .unwrap_or_default(),
+            self.pre_processors.cache_key()?,
+        );
+
+        Some(hash_content(key.as_bytes()))
+    }
+
+    /// Forget about removed files, and all files inside removed folders. Returns the files that
+    /// contributed candidates.
+    fn remove_files(
+        &mut self,
+        removed: Vec<PathBuf>,
+        changes: &mut CandidateChanges,
+    ) -> Vec<PathBuf> {
+        if removed.is_empty() {
+            return vec![];
         }
 
-        // Only keep candidates we haven't seen before
-        for existing in self.candidates.iter() {
-            new_candidates.remove(existing);
+        // The removed paths can't be canonicalized anymore, but their parent folder usually can.
+        // Track both so we match the paths we got from walking as well as the canonical paths.
+        let removed = removed
+            .into_iter()
+            .flat_map(|path| {
+                let canonical = path
+                    .parent()
+                    .and_then(|parent| dunce::canonicalize(parent).ok())
+                    .zip(path.file_name())
+                    .map(|(parent, name)| parent.join(name))
+                    .filter(|canonical| *canonical != path);
+
+                std::iter::once(path).chain(canonical)
+            })
+            .collect::<Vec<_>>();
+
+        let is_removed = |path: &Path| removed.iter().any(|removed| path.starts_with(removed));
+
+        self.files.retain(|file| !is_removed(file));
+        self.dirs.retain(|dir| !is_removed(dir));
+        self.mtimes.retain(|file, _| !is_removed(file));
+        self.stamps.retain(|file, _| !is_removed(file));
+
+        if let Some(usage) = &mut self.usage {
+            usage.retain_files(|file| !is_removed(file));
         }
 
-        // Track new candidates for subsequent calls
-        self.candidates.extend(new_candidates.iter().cloned());
+        self.candidates
+            .retain_files(|file| !is_removed(file), changes)
+    }
 
-        let mut result: Vec<String> = new_candidates.into_iter().collect();
-        result.par_sort_unstable();
+    /// Whether `file` passes the ignore rules and source patterns of the walker, without walking
+    /// the file system. Returns `None` when the file is not inside any of the roots.
+    fn is_allowed(&mut self, file: &Path) -> Option<bool> {
+        let matched = self.match_path(file, false)?;
+        Some(!matched.is_ignore() && self.source_filter.matches(file) && file.is_file())
+    }
+
+    /// Match `path` against the ignore rules of every root it lives in. A path is allowed when
+    /// any of the roots allows it. Returns `None` when the path is not inside any of the roots.
+    fn match_path(&mut self, path: &Path, is_dir: bool) -> Option<IncrementalMatch> {
+        let walker = self.walker.as_ref()?;
+        let matchers = self.matchers.get_or_insert_with(|| walker.build_matchers());
+
+        let mut result = None;
+        for matcher in matchers.iter_mut() {
+            let Some(relative) = matcher.normalize(path) else {
+                continue;
+            };
+
+            let matched = matcher.matched(&relative, is_dir);
+            if !matched.is_ignore() {
+                return Some(matched);
+            }
+
+            result.get_or_insert(matched);
+        }
 
         result
     }
@@ -271,11 +747,33 @@ impl Scanner {
             .collect()
     }
 
+    /// Candidates that are no longer used by any file since the previous `scan()` or
+    /// `scan_content()` call.
+    ///
+    /// This is the `removed` part of the `ScanDiff` returned by `scan_diff()` and
+    /// `scan_content_diff()`, prefer those when both the added and removed candidates are needed.
+    #[tracing::instrument(skip_all)]
+    pub fn get_removed_candidates(&self) -> Vec<String> {
+        self.removed_candidates.clone()
+    }
+
     #[tracing::instrument(skip_all)]
     pub fn get_scanned_files(&self) -> Vec<String> {
         self.scanned_files.clone()
     }
 
+    /// How often every candidate occurs in all scanned files and content. Only available when
+    /// usage statistics are enabled.
+    pub fn get_usage_report(&self) -> Option<UsageReport> {
+        self.usage.as_ref().map(UsageIndex::report)
+    }
+
+    /// Numbers about the last `scan()`, `scan_diff()`, `scan_content()` or `scan_content_diff()`
+    /// call.
+    pub fn get_scan_stats(&self) -> &ScanStats {
+        &self.stats
+    }
+
     #[tracing::instrument(skip_all)]
     pub fn get_globs(&mut self) -> Vec<GlobEntry> {
         if let Some(globs) = &self.globs {
@@ -292,6 +790,7 @@ impl Scanner {
                         base.to_path_buf(),
                         &self.dirs,
                         &self.extensions,
+                        &self.auto_source_detection.ignored_content_dirs,
                     ));
                 }
                 SourceEntry::Pattern { base, pattern } => {
@@ -331,68 +830,265 @@ impl Scanner {
             .collect()
     }
 
-    #[tracing::instrument(skip_all)]
+    /// Returns every candidate and CSS variable in the content, together with its byte offset.
     pub fn get_candidates_with_positions(
         &mut self,
         changed_content: ChangedContent,
-    ) -> Vec<(String, usize)> {
-        let content = read_changed_content(changed_content).unwrap_or_default();
-        let original_content = &content;
-
-        // Workaround for legacy upgrades:
-        //
-        // `-[]` won't parse in the new parser (`[…]` must contain _something_), but we do need it
-        // for people using `group-[]` (which we will later replace with `in-[.group]` instead).
-        let content = content.replace("-[]", "XYZ");
-        let offset = content.as_ptr() as usize;
-
-        let mut extractor = Extractor::new(&content[..]);
-
-        extractor
-            .extract()
-            .into_par_iter()
-            .flat_map(|extracted| match extracted {
-                Extracted::Candidate(s) => {
-                    let i = s.as_ptr() as usize - offset;
-                    let original = &original_content[i..i + s.len()];
-                    if original.contains_str("-[]") {
-                        return String::from_utf8(original.to_vec())
-                            .ok()
-                            .map(|candidate| (candidate, i));
+    ) -> Vec<(String, usize, ExtractedKind)> {
+        self.get_candidates_with_positions_and_content(changed_content)
+            .1
+    }
+
+    /// Like `get_candidates_with_positions`, but also returns the content the candidates were
+    /// extracted from. Offsets point to the original bytes of files that were transcoded to UTF-8,
+    /// `FileContent::decoded_offset` maps them back into the content. The content is empty when
+    /// the file can't be read.
+    #[tracing::instrument(skip_all)]
+    pub fn get_candidates_with_positions_and_content(
+        &mut self,
+        changed_content: ChangedContent,
+    ) -> (FileContent, Vec<(String, usize, ExtractedKind)>) {
+        // Explicitly requested content is only skipped when it is binary
+        let content = read_changed_content(
+            changed_content,
+            &self.pre_processors,
+            &ContentLimits::default(),
+            &PhaseCounters::default(),
+            false,
+        )
+        .and_then(Result::ok)
+        .map(|(content, _)| content)
+        .unwrap_or_default();
+
+        let positions = candidates_with_positions(&content, &self.extractor_options)
+            .into_iter()
+            .map(|(candidate, offset, kind)| (candidate, content.original_offset(offset), kind))
+            .collect();
+
+        (content, positions)
+    }
+
+    /// Returns every occurrence of every candidate and CSS variable in all files of the sources,
+    /// sorted by file and position.
+    ///
+    /// Every tracked file is read again once, in parallel. Unlike `get_candidate_locations`, this
+    /// includes candidates that were not added to the known candidates, because they come from a
+    /// file that changed since the last scan for example.
+    pub fn get_all_candidates_with_positions(&mut self) -> Vec<CandidatePosition> {
+        self.map_all_candidates_with_positions(|_, positions| positions)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Like `get_all_candidates_with_positions`, but calls `f` with the positions of every file,
+    /// together with the content they were extracted from, and returns the results sorted by file.
+    ///
+    /// Offsets point to the original bytes of files that were transcoded to UTF-8,
+    /// `FileContent::decoded_offset` maps them back into the content. The content is dropped once
+    /// `f` returns, so not every file has to be kept in memory at the same time.
+    #[tracing::instrument(skip_all)]
+    pub fn map_all_candidates_with_positions<T, F>(&mut self, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&FileContent, Vec<CandidatePosition>) -> T + Sync,
+    {
+        if !self.has_scanned_once {
+            self.scan_diff();
+        }
+
+        // `self.files` also holds the walked path of symlinked files, only read every tracked file
+        // once through the canonical path the candidate index uses.
+        let mut results = self
+            .candidates
+            .files()
+            .par_bridge()
+            .filter_map(|(file, _)| {
+                let extension = file
+                    .extension()
+                    .and_then(|x| x.to_str())
+                    .unwrap_or_default();
+                let blob = read_changed_content(
+                    ChangedContent::File(file.clone(), extension.to_owned()),
+                    &self.pre_processors,
+                    &self.limits,
+                    &PhaseCounters::default(),
+                    false,
+                )?
+                .map(|(content, _)| content)
+                .unwrap_or_default();
+
+                let found = match extension {
+                    "css" => {
+                        extract_with_offsets(&blob, &self.extractor_options, |mut extractor| {
+                            extractor.extract_variables_from_css()
+                        })
+                        .into_iter()
+                        .filter_map(|(bytes, offset, kind)| {
+                            let variable = String::from_utf8(bytes.to_vec()).ok()?;
+                            Some((variable, offset, kind))
+                        })
+                        .collect()
+                    }
+                    _ => candidates_with_positions(&blob, &self.extractor_options),
+                };
+
+                let line_starts = line_starts(&blob);
+                let mut positions = found
+                    .into_iter()
+                    .map(|(candidate, start, kind)| {
+                        let (line, column) = line_and_column(&line_starts, start);
+
+                        // Positions point to the file, not to the transcoded content
+                        let line_start = blob.original_offset(start - column);
+                        let end = blob.original_offset(start + candidate.len());
+                        let start = blob.original_offset(start);
+
+                        CandidatePosition {
+                            file: file.clone(),
+                            range: start..end,
+                            candidate,
+                            kind,
+                            line,
+                            column: start - line_start,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                positions.sort_unstable_by(|a, b| {
+                    a.range
+                        .start
+                        .cmp(&b.range.start)
+                        .then(a.range.end.cmp(&b.range.end))
+                });
+                positions.dedup_by(|a, b| a.range == b.range && a.kind == b.kind);
+
+                Some((file, f(&blob, positions)))
+            })
+            .collect::<Vec<_>>();
+
+        results.par_sort_unstable_by(|a, b| a.0.cmp(b.0));
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Returns every known candidate together with the files and byte offsets it was found at.
+    ///
+    /// Only the files that produced candidates are read again, the offsets are relative to the
+    /// pre-processed content of each file, and point to the original bytes of files that were
+    /// transcoded to UTF-8. Candidates from raw content (`ChangedContent::Content`)
+    /// have no file to point to and are not part of the result.
+    #[tracing::instrument(skip_all)]
+    pub fn get_candidate_locations(&self) -> Vec<(String, Vec<CandidateLocation>)> {
+        let found = self
+            .candidates
+            .files()
+            .par_bridge()
+            .filter(|(_, candidates)| !candidates.is_empty())
+            .filter_map(|(file, candidates)| {
+                let extension = file
+                    .extension()
+                    .and_then(|x| x.to_str())
+                    .unwrap_or_default();
+                let blob = read_changed_content(
+                    ChangedContent::File(file.clone(), extension.to_owned()),
+                    &self.pre_processors,
+                    &self.limits,
+                    &PhaseCounters::default(),
+                    false,
+                )?
+                .map(|(content, _)| content)
+                .unwrap_or_default();
+
+                let extracted = match extension {
+                    "css" => {
+                        extract_with_offsets(&blob, &self.extractor_options, |mut extractor| {
+                            extractor.extract_variables_from_css()
+                        })
                     }
+                    _ => extract_with_offsets(&blob, &self.extractor_options, |mut extractor| {
+                        extractor.extract()
+                    }),
+                };
+
+                // The file could have changed since it was scanned, only report the candidates
+                // we are actually tracking for this file.
+                let mut offsets: FxHashMap<&str, Vec<usize>> = FxHashMap::default();
+                for (bytes, offset, _) in extracted {
+                    let Ok(candidate) = std::str::from_utf8(bytes) else {
+                        continue;
+                    };
 
-                    String::from_utf8(s.to_vec())
-                        .ok()
-                        .map(|candidate| (candidate, i))
+                    if let Some(candidate) = candidates.get(candidate) {
+                        offsets
+                            .entry(candidate.as_str())
+                            .or_default()
+                            .push(blob.original_offset(offset));
+                    }
                 }
 
-                _ => None,
+                Some(
+                    offsets
+                        .into_iter()
+                        .map(|(candidate, offsets)| {
+                            (
+                                candidate.to_owned(),
+                                CandidateLocation {
+                                    file: file.clone(),
+                                    offsets,
+                                },
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
             })
-            .collect()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut locations: FxHashMap<String, Vec<CandidateLocation>> = FxHashMap::default();
+        for (candidate, location) in found {
+            locations.entry(candidate).or_default().push(location);
+        }
+
+        let mut result = locations
+            .into_iter()
+            .map(|(candidate, mut locations)| {
+                locations.sort_unstable_by(|a, b| a.file.cmp(&b.file));
+                (candidate, locations)
+            })
+            .collect::<Vec<_>>();
+        result.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        result
     }
 
     #[tracing::instrument(skip_all)]
-    fn discover_sources(&mut self) -> (Vec<Vec<u8>>, Vec<PathBuf>, Vec<String>) {
+    fn discover_sources(&mut self) -> DiscoveredSources {
         if self.sources_scanned {
-            return (vec![], vec![], vec![]);
+            return DiscoveredSources::default();
         }
         self.sources_scanned = true;
 
-        let Some(walker) = &mut self.walker else {
-            return (vec![], vec![], vec![]);
+        // The persistent cache is only useful for the initial scan, incremental scans already know
+        // about all the files. It doesn't know how often candidates occur, so it can't be used
+        // when we have to count them.
+        let cache = match &self.cache_file {
+            Some(cache_file) if !self.has_scanned_once && self.usage.is_none() => self
+                .cache_key()
+                .and_then(|options| ScanCache::load(cache_file, options)),
+            _ => None,
         };
 
-        // Use synchronous walk for the initial build (lower overhead) and parallel
-        // walk for subsequent calls (watch mode) where the overhead is amortised.
-        let all_entries = if self.has_scanned_once {
-            walk_parallel(walker)
-        } else {
-            walk_synchronous(walker)
+        let track_stamps = self.tracks_stamps();
+        let Some(walker) = &mut self.walker else {
+            return DiscoveredSources::default();
         };
 
-        let mut css_files: Vec<PathBuf> = vec![];
-        let mut content_paths: Vec<(PathBuf, String)> = vec![];
-        let mut changed_files = vec![];
+        let started = Instant::now();
+        let counters = PhaseCounters::default();
+        let previous_walk = self.last_walk.replace(SystemTime::now());
+
+        let mut discovered = DiscoveredSources::default();
+        let compare_contents =
+            self.has_scanned_once && self.change_detection == ChangeDetection::ContentHash;
 
         // Fresh state
         self.files.clear();
@@ -404,164 +1100,534 @@ impl Scanner {
         // folders are symlinked.
         let mut cached_canonical_dirs: FxHashMap<PathBuf, PathBuf> = FxHashMap::default();
 
-        for entry in all_entries {
-            match entry {
-                WalkEntry::Dir(path) => {
-                    self.dirs.insert(path);
+        let count_usage = self.usage.is_some();
+        let context = ReadContext {
+            track_stamps,
+            cache: cache.as_ref(),
+            memory_map: !self.has_scanned_once,
+            pre_processors: &self.pre_processors,
+            limits: &self.limits,
+            extractor_options: &self.extractor_options,
+            count_usage,
+            counters: &counters,
+        };
+        let context = &context;
+        let has_scanned_once = self.has_scanned_once;
+
+        // Walking, reading + extracting, and keeping track of the discovered files all happen at
+        // the same time, connected by bounded channels. Every file is read, pre-processed and
+        // extracted by a single task, which drops the content as soon as the candidates are
+        // known. This way only a few files per thread are in memory at the same time.
+        let read_files = std::thread::scope(|scope| {
+            let (entry_sender, entries) = sync_channel(PIPELINE_CAPACITY);
+            let (read_sender, pending_reads) = sync_channel::<PendingRead>(PIPELINE_CAPACITY);
+
+            // Use synchronous walk for the initial build (lower overhead) and parallel
+            // walk for subsequent calls (watch mode) where the overhead is amortised.
+            let walking = &counters.walking;
+            scope.spawn(move || {
+                let started = Instant::now();
+                if has_scanned_once {
+                    walk_parallel(walker, entry_sender)
+                } else {
+                    walk_synchronous(walker, entry_sender)
                 }
-                WalkEntry::File {
-                    path,
-                    mtime,
-                    is_symlink,
-                } => {
-                    // Deduplicate: parallel walk can visit the same file from multiple threads
-                    if !self.files.insert(path.clone()) {
-                        continue;
+                walking.add_time(started);
+            });
+
+            let reader = scope.spawn(move || {
+                pending_reads
+                    .into_iter()
+                    .par_bridge()
+                    .filter_map(|pending| read_pending(pending, context))
+                    .collect::<Vec<_>>()
+            });
+
+            for entry in entries.into_iter().flatten() {
+                match entry {
+                    WalkEntry::Dir(path) => {
+                        self.dirs.insert(path);
                     }
+                    WalkEntry::File {
+                        path,
+                        mtime,
+                        size,
+                        is_symlink,
+                    } => {
+                        // Deduplicate: parallel walk can visit the same file from multiple threads
+                        if !self.files.insert(path.clone()) {
+                            continue;
+                        }
+                        counters.walking.add_file(size);
+
+                        // Track canonicalized paths in addition to potentially symlinked file paths
+                        let canonical = if is_symlink {
+                            dunce::canonicalize(&path).ok()
+                        } else {
+                            path.parent().and_then(|parent| {
+                                // Perf: cache the canonicalized parent path such that sibling files
+                                // don't have to canonicalize over and over again.
+                                let canonical_parent = cached_canonical_dirs
+                                    .entry(parent.to_path_buf())
+                                    .or_insert_with(|| {
+                                        dunce::canonicalize(parent)
+                                            .unwrap_or_else(|_| parent.to_path_buf())
+                                    });
+
+                                if canonical_parent.as_path() != parent {
+                                    path.file_name()
+                                        .map(|file_name| canonical_parent.join(file_name))
+                                } else {
+                                    None
+                                }
+                            })
+                        };
 
-                    // Track canonicalized paths in addition to potentially symlinked file paths
-                    let canonical = if is_symlink {
-                        dunce::canonicalize(&path).ok()
-                    } else {
-                        path.parent().and_then(|parent| {
-                            // Perf: cache the canonicalized parent path such that sibling files don't
-                            // have to canonicalize over and over again.
-                            let canonical_parent = cached_canonical_dirs
-                                .entry(parent.to_path_buf())
-                                .or_insert_with(|| {
-                                    dunce::canonicalize(parent)
-                                        .unwrap_or_else(|_| parent.to_path_buf())
-                                });
+                        // Candidates are tracked by the canonical path, so the same file is known
+                        // under the same key whether it was walked through a symlink or passed to
+                        // `scan_content` directly.
+                        let tracked_path = match canonical {
+                            Some(canonical) if canonical != path => {
+                                self.files.insert(canonical.clone());
+                                canonical
+                            }
+                            _ => path.clone(),
+                        };
+
+                        let extension = path
+                            .extension()
+                            .and_then(|x| x.to_str())
+                            .unwrap_or_default()
+                            .to_owned();
+
+                        self.extensions.insert(extension.to_owned());
+
+                        // When comparing contents, the mtime and size are only a pre-filter. Files
+                        // that pass it are read, but only reported as changed when their contents
+                        // differ.
+                        if compare_contents {
+                            let previous = self.stamps.get(&tracked_path).copied();
+                            let untouched = previous.is_some_and(|previous| {
+                                mtime.is_some_and(|mtime| {
+                                    previous.mtime == Some(mtime)
+                                        && previous.size == size
+                                        && previous_walk
+                                            .is_some_and(|walk| mtime + RACY_MTIME_WINDOW < walk)
+                                })
+                            });
 
-                            if canonical_parent.as_path() != parent {
-                                path.file_name()
-                                    .map(|file_name| canonical_parent.join(file_name))
-                            } else {
-                                None
+                            if !untouched {
+                                _ = read_sender.send(PendingRead {
+                                    path,
+                                    tracked_path,
+                                    extension,
+                                    mtime,
+                                    size,
+                                    previous,
+                                });
                             }
-                        })
-                    };
 
-                    if let Some(canonical) = canonical {
-                        if canonical != path {
-                            self.files.insert(canonical);
+                            continue;
                         }
-                    }
-                    let extension = path
-                        .extension()
-                        .and_then(|x| x.to_str())
-                        .unwrap_or_default()
-                        .to_owned();
-
-                    self.extensions.insert(extension.to_owned());
-
-                    // On incremental scans, check mtime to skip unchanged files.
-                    // On the first scan, track mtimes while still scanning every file.
-                    let changed = if self.has_scanned_once {
-                        match mtime {
-                            Some(mtime) => {
-                                let prev = self.mtimes.insert(path.clone(), mtime);
-                                prev.is_none_or(|prev| prev != mtime)
+
+                        // On incremental scans, check mtime to skip unchanged files.
+                        // On the first scan, track mtimes while still scanning every file.
+                        let changed = if self.has_scanned_once {
+                            match mtime {
+                                Some(mtime) => {
+                                    let prev = self.mtimes.insert(path.clone(), mtime);
+                                    prev.is_none_or(|prev| prev != mtime)
+                                }
+                                None => true,
+                            }
+                        } else {
+                            if let Some(mtime) = mtime {
+                                self.mtimes.insert(path.clone(), mtime);
                             }
-                            None => true,
-                        }
-                    } else {
-                        if let Some(mtime) = mtime {
-                            self.mtimes.insert(path.clone(), mtime);
-                        }
 
-                        true
-                    };
+                            true
+                        };
 
-                    if !changed {
-                        continue;
-                    }
+                        if !changed {
+                            continue;
+                        }
 
-                    if let Ok(file) = path.clone().into_os_string().into_string() {
-                        changed_files.push(file);
-                    }
+                        // Reuse the candidates from the persistent cache when the file didn't
+                        // change since the cache was written.
+                        if let Some((stamp, candidates)) = context
+                            .cache
+                            .and_then(|cache| cache.unchanged(&tracked_path, mtime, size))
+                        {
+                            discovered.push_changed_file(path, &tracked_path);
+
+                            self.stamps.insert(tracked_path.clone(), stamp);
+                            discovered.candidates.push((tracked_path, candidates));
+                            continue;
+                        }
 
-                    match extension.as_str() {
-                        // Special handing for CSS files, we don't want to extract candidates from
-                        // these files, but we do want to extract used CSS variables.
-                        "css" => css_files.push(path),
-                        _ => content_paths.push((path, extension)),
+                        _ = read_sender.send(PendingRead {
+                            path,
+                            tracked_path,
+                            extension,
+                            mtime,
+                            size,
+                            previous: None,
+                        });
                     }
                 }
             }
-        }
+
+            // No more files to read
+            drop(read_sender);
+
+            reader.join().unwrap()
+        });
 
         // Ensure `mtimes` don't include stale files
         self.mtimes.retain(|path, _| self.files.contains(path));
 
-        // Read + preprocess all discovered files in parallel
-        let scanned_blobs: Vec<Vec<u8>> = content_paths
-            .into_par_iter()
-            .filter_map(|(path, ext)| {
-                let content = std::fs::read(&path).ok()?;
-                event!(tracing::Level::INFO, "Reading {:?}", path);
-                let processed = pre_process_input(content, &ext);
-                if processed.is_empty() {
-                    None
-                } else {
-                    Some(processed)
+        for (walked_path, path, stamp, read_file) in read_files {
+            if let Some(stamp) = stamp {
+                self.stamps.insert(path.clone(), stamp);
+            }
+
+            match read_file {
+                ReadFile::Unchanged => continue,
+
+                // Forget the candidates of files that were scanned before they were skipped
+                ReadFile::Skipped(reason) => {
+                    discovered.stats.record_skipped(&walked_path, reason);
+                    discovered.usage.push((path.clone(), None));
+                    discovered.candidates.push((path, FxHashSet::default()));
+                    continue;
                 }
-            })
-            .collect();
+
+                ReadFile::Candidates(_, _) => {}
+            }
+
+            discovered.push_changed_file(walked_path, &path);
+
+            if let ReadFile::Candidates(candidates, usage) = read_file {
+                if count_usage {
+                    discovered.usage.push((path.clone(), usage));
+                }
+                discovered.candidates.push((path, candidates));
+            }
+        }
 
         if !self.has_scanned_once {
             self.has_scanned_once = true;
         }
 
-        changed_files.par_sort_unstable();
+        discovered.changed_files.par_sort_unstable();
+        discovered.changed_tracked_files.par_sort_unstable();
+        discovered.stats.finish(started, &counters);
 
-        (scanned_blobs, css_files, changed_files)
+        discovered
     }
 }
 
-fn read_changed_content(c: ChangedContent) -> Option<Vec<u8>> {
-    let (content, extension) = match c {
-        ChangedContent::File(file, extension) => match std::fs::read(&file) {
-            Ok(content) => {
+/// Reads and pre-processes the content. Changed files can still be written to, so they are read
+/// into memory instead of memory mapped.
+///
+/// When `track_stamps` is set, the stamp of a file is computed from its bytes on disk, before they
+/// are transcoded or pre-processed, like `read_pending` does.
+fn read_changed_content(
+    c: ChangedContent,
+    pre_processors: &PreProcessorRegistry,
+    limits: &ContentLimits,
+    counters: &PhaseCounters,
+    track_stamps: bool,
+) -> Option<Result<(FileContent, Option<FileStamp>), SkipReason>> {
+    let (content, file, extension, stamp) = match c {
+        ChangedContent::File(file, extension) => match read_file(&file, limits, counters) {
+            Ok(Ok(content)) => {
                 event!(tracing::Level::INFO, "Reading {:?}", file);
-                (content, extension)
+                let stamp = track_stamps.then(|| {
+                    let mtime = std::fs::metadata(&file).and_then(|metadata| metadata.modified());
+                    content.stamp(mtime.ok())
+                });
+
+                (content, Some(file), extension, stamp)
             }
+            Ok(Err(reason)) => return Some(Err(reason)),
+
             Err(e) => {
                 event!(tracing::Level::ERROR, "Failed to read file: {:?}", e);
                 return None;
             }
         },
 
-        ChangedContent::Content(contents, extension) => (contents.into_bytes(), extension),
+        ChangedContent::Content(contents, extension) => (
+            FileContent::Buffered(contents.into_bytes()),
+            None,
+            extension,
+            None,
+        ),
+
+        // There is nothing to read
+        ChangedContent::Removed(_) | ChangedContent::Renamed(_, _, _) => return None,
     };
 
-    Some(pre_process_input(content, &extension))
+    let content = pre_process(
+        content,
+        file.as_deref(),
+        &extension,
+        pre_processors,
+        counters,
+    );
+
+    Some(Ok((content, stamp)))
+}
+
+/// Reads a file, unless it is skipped because of the `limits`.
+fn read_file(
+    path: &Path,
+    limits: &ContentLimits,
+    counters: &PhaseCounters,
+) -> std::io::Result<Result<FileContent, SkipReason>> {
+    if let Some(reason) = limits.check_size(std::fs::metadata(path)?.len()) {
+        log_skipped(path, reason);
+        return Ok(Err(reason));
+    }
+
+    let started = Instant::now();
+    let content = FileContent::read(path)?;
+    counters.reading.record(started, content.len() as u64);
+    if let Some(reason) = limits.check_content(&content) {
+        log_skipped(path, reason);
+        return Ok(Err(reason));
+    }
+
+    Ok(Ok(content))
+}
+
+fn log_skipped(path: &Path, reason: SkipReason) {
+    event!(tracing::Level::INFO, "Skipping {:?}: {}", path, reason);
+}
+
+/// Pre-processes the content, without copying it when there is no pre-processor for the file.
+fn pre_process(
+    content: FileContent,
+    file: Option<&Path>,
+    extension: &str,
+    pre_processors: &PreProcessorRegistry,
+    counters: &PhaseCounters,
+) -> FileContent {
+    let Some(pre_processor) = pre_processors.resolve(file, extension) else {
+        return content;
+    };
+
+    let started = Instant::now();
+    let processed = process_checking_offsets(pre_processor, &content);
+    counters
+        .pre_processing
+        .record(started, content.len() as u64);
+
+    content.with_content(processed)
 }
 
 pub fn pre_process_input(content: Vec<u8>, extension: &str) -> Vec<u8> {
-    use crate::extractor::pre_processors::*;
-
-    match extension {
-        "clj" | "cljs" | "cljc" => Clojure.process(&content),
-        "heex" | "eex" | "ex" | "exs" => Elixir.process(&content),
-        "cshtml" | "razor" => Razor.process(&content),
-        "haml" => Haml.process(&content),
-        "json" | "jsonl" | "ndjson" => Json.process(&content),
-        "md" | "mdx" => Markdown.process(&content),
-        "pug" => Pug.process(&content),
-        "rb" | "erb" => Ruby.process(&content),
-        "slim" | "slang" => Slim.process(&content),
-        "svelte" => Svelte.process(&content),
-        "rs" => Rust.process(&content),
-        "tt" | "tt2" | "tx" => TemplateToolkit.process(&content),
-        "twig" => Twig.process(&content),
-        "vue" => Vue.process(&content),
-        _ => content,
+    match BuiltinPreProcessor::for_extension(extension) {
+        BuiltinPreProcessor::None => content,
+        pre_processor => process_checking_offsets(&pre_processor, &content),
+    }
+}
+
+/// Everything `discover_sources` found that is new or changed since the last scan.
+#[derive(Debug, Default)]
+struct DiscoveredSources {
+    /// Candidates of every new or changed file, either extracted or from the persistent cache
+    candidates: Vec<(PathBuf, FxHashSet<String>)>,
+
+    /// All new or changed files, by their walked path
+    changed_files: Vec<String>,
+
+    /// All new or changed files, by the canonical path their candidates are tracked by
+    changed_tracked_files: Vec<String>,
+
+    stats: ScanStats,
+
+    /// Usage of every new, changed or skipped file, only tracked when usage statistics are enabled
+    usage: Vec<(PathBuf, Option<ContentUsage>)>,
+}
+
+impl DiscoveredSources {
+    fn push_changed_file(&mut self, walked_path: PathBuf, tracked_path: &Path) {
+        if let Ok(file) = walked_path.into_os_string().into_string() {
+            self.changed_files.push(file);
+        }
+
+        if let Some(file) = tracked_path.to_str() {
+            self.changed_tracked_files.push(file.to_owned());
+        }
+    }
+}
+
+/// The result of reading a single discovered file.
+#[derive(Debug)]
+enum ReadFile {
+    /// The candidates, and how often they occur when usage statistics are enabled
+    Candidates(FxHashSet<String>, Option<ContentUsage>),
+
+    /// The contents are the same as during the last scan
+    Unchanged,
+
+    /// The file is too large, looks minified or looks binary
+    Skipped(SkipReason),
+}
+
+/// A discovered file that has to be read.
+#[derive(Debug)]
+struct PendingRead {
+    /// The path as it was walked, this is the path we report
+    path: PathBuf,
+
+    /// The canonical path, this is the path candidates are tracked by
+    tracked_path: PathBuf,
+
+    extension: String,
+    mtime: Option<SystemTime>,
+    size: u64,
+
+    /// The stamp of the file during the last scan, when comparing contents
+    previous: Option<FileStamp>,
+}
+
+/// Everything that is shared between the reads of a single `discover_sources` call.
+struct ReadContext<'a> {
+    /// Whether the size and content hash of every file is tracked
+    track_stamps: bool,
+
+    /// The persistent cache, only used for the initial scan
+    cache: Option<&'a ScanCache>,
+
+    /// Whether large files are memory mapped, only done for the initial scan. Files change all
+    /// the time while watching, and a mapped file that is truncated crashes the process.
+    memory_map: bool,
+
+    pre_processors: &'a PreProcessorRegistry,
+    limits: &'a ContentLimits,
+    extractor_options: &'a ExtractorOptions,
+    count_usage: bool,
+    counters: &'a PhaseCounters,
+}
+
+/// Read a discovered file, and extract its candidates.
+fn read_pending(
+    pending: PendingRead,
+    context: &ReadContext,
+) -> Option<(PathBuf, PathBuf, Option<FileStamp>, ReadFile)> {
+    let ReadContext {
+        track_stamps,
+        cache,
+        memory_map,
+        pre_processors,
+        limits,
+        extractor_options,
+        count_usage,
+        counters,
+    } = *context;
+
+    let PendingRead {
+        path: walked_path,
+        tracked_path: path,
+        extension: ext,
+        mtime,
+        size,
+        previous,
+    } = pending;
+
+    if let Some(reason) = limits.check_size(size) {
+        log_skipped(&walked_path, reason);
+        return Some((walked_path, path, None, ReadFile::Skipped(reason)));
+    }
+
+    let started = Instant::now();
+    let content = if memory_map {
+        FileContent::read_mapped(&path)
+    } else {
+        FileContent::read(&path)
     }
+    .ok()?;
+    counters.reading.record(started, content.len() as u64);
+    event!(tracing::Level::INFO, "Reading {:?}", path);
+
+    let stamp = track_stamps.then(|| content.stamp(mtime));
+
+    if let Some(reason) = limits.check_content(&content) {
+        log_skipped(&walked_path, reason);
+        return Some((walked_path, path, stamp, ReadFile::Skipped(reason)));
+    }
+
+    // The file was touched, but its contents didn't change since the last scan
+    if previous.is_some_and(|previous| {
+        stamp.is_some_and(|stamp| previous.size == stamp.size && previous.hash == stamp.hash)
+    }) {
+        return Some((walked_path, path, stamp, ReadFile::Unchanged));
+    }
+
+    // The file was touched, but its contents are the same as in the persistent cache
+    if let Some(candidates) = cache
+        .zip(stamp.as_ref())
+        .and_then(|(cache, stamp)| cache.same_content(&path, stamp))
+    {
+        return Some((
+            walked_path,
+            path,
+            stamp,
+            ReadFile::Candidates(candidates, None),
+        ));
+    }
+
+    let (candidates, usage) = match ext.as_str() {
+        // Special handing for CSS files, we don't want to extract candidates from
+        // these files, but we do want to extract used CSS variables.
+        "css" => extract_candidates(
+            &content,
+            &ext,
+            extractor_options,
+            count_usage,
+            counters,
+            |mut extractor| extractor.extract_variables_from_css(),
+        ),
+        _ => extract_candidates(
+            &pre_process(content, Some(&path), &ext, pre_processors, counters),
+            &ext,
+            extractor_options,
+            count_usage,
+            counters,
+            |mut extractor| extractor.extract(),
+        ),
+    };
+
+    Some((
+        walked_path,
+        path,
+        stamp,
+        ReadFile::Candidates(candidates, usage),
+    ))
 }
 
+/// The candidates of changed content and how often they occur, or the reason the file was skipped.
+type ContentCandidates = Result<(FxHashSet<String>, Option<ContentUsage>), SkipReason>;
+
+/// Extracted changed content: the file (`None` for raw content), the stamp of the file when stamps
+/// are tracked, and its candidates.
+type ExtractedContent = (Option<PathBuf>, Option<FileStamp>, ContentCandidates);
+
+/// Reads and extracts the candidates of all changed content, the content is dropped as soon as
+/// its candidates are known. Raw content (`ChangedContent::Content`) has no file.
 #[tracing::instrument(skip_all)]
-fn read_all_files(changed_content: Vec<ChangedContent>) -> Vec<Vec<u8>> {
+fn extract_all_content(
+    changed_content: Vec<ChangedContent>,
+    pre_processors: &PreProcessorRegistry,
+    limits: &ContentLimits,
+    extractor_options: &ExtractorOptions,
+    count_usage: bool,
+    counters: &PhaseCounters,
+    track_stamps: bool,
+) -> Vec<ExtractedContent> {
     event!(
         tracing::Level::INFO,
         "Reading {:?} file(s)",
@@ -570,36 +1636,108 @@ fn read_all_files(changed_content: Vec<ChangedContent>) -> Vec<Vec<u8>> {
 
     changed_content
         .into_par_iter()
-        .filter_map(read_changed_content)
+        .filter_map(|changed_content| {
+            let (file, extension) = match &changed_content {
+                ChangedContent::File(file, extension) => (Some(file.clone()), extension.clone()),
+                ChangedContent::Content(_, extension) => (None, extension.clone()),
+                _ => (None, String::new()),
+            };
+
+            let mut stamp = None;
+            let candidates = read_changed_content(
+                changed_content,
+                pre_processors,
+                limits,
+                counters,
+                track_stamps,
+            )?
+            .map(|(blob, blob_stamp)| {
+                stamp = blob_stamp;
+                extract_candidates(
+                    &blob,
+                    &extension,
+                    extractor_options,
+                    count_usage,
+                    counters,
+                    |mut extractor| extractor.extract(),
+                )
+            });
+
+            Some((file, stamp, candidates))
+        })
         .collect()
 }
 
-#[tracing::instrument(skip_all)]
-fn extract_css_variables(blobs: Vec<Vec<u8>>) -> FxHashSet<String> {
-    extract(blobs, |mut extractor| {
-        extractor.extract_variables_from_css()
-    })
+/// Extracts the unique set of candidates of a blob, and how often they occur when `count_usage` is
+/// set.
+fn extract_candidates<H>(
+    blob: &[u8],
+    extension: &str,
+    options: &ExtractorOptions,
+    count_usage: bool,
+    counters: &PhaseCounters,
+    handle: H,
+) -> (FxHashSet<String>, Option<ContentUsage>)
+where
+    H: Fn(Extractor) -> Vec<Extracted> + std::marker::Sync,
+{
+    let started = Instant::now();
+    let (candidates, usage) = if count_usage {
+        let usage = ContentUsage {
+            extension: extension.to_owned(),
+            counts: count_blob(blob, options, handle),
+        };
+
+        (usage.counts.keys().cloned().collect(), Some(usage))
+    } else {
+        (extract_blob(blob, options, handle), None)
+    };
+    counters.extraction.record(started, blob.len() as u64);
+
+    (candidates, usage)
 }
 
-#[tracing::instrument(skip_all)]
-fn parse_all_blobs(blobs: Vec<Vec<u8>>) -> FxHashSet<String> {
-    extract(blobs, |mut extractor| extractor.extract())
+/// Extracts every candidate of a blob, together with the number of times it occurs.
+fn count_blob<H>(blob: &[u8], options: &ExtractorOptions, handle: H) -> FxHashMap<String, usize>
+where
+    H: Fn(Extractor) -> Vec<Extracted> + std::marker::Sync,
+{
+    blob.par_split(|x| *x == b'\n')
+        .filter(|blob| !blob.is_empty())
+        .map(|blob| {
+            let mut counts: FxHashMap<&[u8], usize> = FxHashMap::default();
+            for extracted in handle(Extractor::new_with_options(blob, options)) {
+                let bytes = match extracted {
+                    Extracted::Candidate(bytes) => bytes,
+                    Extracted::CssVariable(bytes) => bytes,
+                };
+                *counts.entry(bytes).or_default() += 1;
+            }
+            counts
+        })
+        .reduce(Default::default, |mut a, b| {
+            for (bytes, count) in b {
+                *a.entry(bytes).or_default() += count;
+            }
+            a
+        })
+        .into_iter()
+        .filter_map(|(bytes, count)| Some((String::from_utf8(bytes.to_vec()).ok()?, count)))
+        .collect()
 }
 
-#[tracing::instrument(skip_all)]
-fn extract<H>(blobs: Vec<Vec<u8>>, handle: H) -> FxHashSet<String>
+/// Extracts the unique set of candidates of a blob.
+fn extract_blob<H>(blob: &[u8], options: &ExtractorOptions, handle: H) -> FxHashSet<String>
 where
     H: Fn(Extractor) -> Vec<Extracted> + std::marker::Sync,
 {
-    blobs
-        .par_iter()
-        .flat_map(|blob| blob.par_split(|x| *x == b'\n'))
+    blob.par_split(|x| *x == b'\n')
         .filter_map(|blob| {
             if blob.is_empty() {
                 return None;
             }
 
-            let extracted = handle(crate::extractor::Extractor::new(blob));
+            let extracted = handle(Extractor::new_with_options(blob, options));
             if extracted.is_empty() {
                 return None;
             }
@@ -620,12 +1758,82 @@ where
         .collect()
 }
 
+/// Extracts every candidate and CSS variable of the content together with its byte offset,
+/// including legacy candidates like `group-[]:flex`.
+fn candidates_with_positions(
+    content: &[u8],
+    options: &ExtractorOptions,
+) -> Vec<(String, usize, ExtractedKind)> {
+    let original_content = content;
+
+    // Workaround for legacy upgrades:
+    //
+    // `-[]` won't parse in the new parser (`[…]` must contain _something_), but we do need it
+    // for people using `group-[]` (which we will later replace with `in-[.group]` instead).
+    let content = content.replace("-[]", "XYZ");
+
+    extract_with_offsets(&content, options, |mut extractor| extractor.extract())
+        .into_iter()
+        .filter_map(|(bytes, i, kind)| {
+            let original = &original_content[i..i + bytes.len()];
+            let bytes = match kind {
+                ExtractedKind::Candidate if original.contains_str("-[]") => original,
+                _ => bytes,
+            };
+
+            String::from_utf8(bytes.to_vec())
+                .ok()
+                .map(|candidate| (candidate, i, kind))
+        })
+        .collect()
+}
+
+/// The byte offset of the start of every line.
+fn line_starts(content: &[u8]) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(content.find_iter(b"\n").map(|idx| idx + 1))
+        .collect()
+}
+
+/// The 1-based line, and the 0-based byte column of `offset`.
+fn line_and_column(line_starts: &[usize], offset: usize) -> (usize, usize) {
+    let line = line_starts.partition_point(|start| *start <= offset);
+    (line, offset - line_starts[line - 1])
+}
+
+/// Extracts every occurrence of a candidate in the blob together with its byte offset. This uses
+/// the same line based splitting as `extract_blob`, so both agree on what the candidates are.
+fn extract_with_offsets<'a, H>(
+    blob: &'a [u8],
+    options: &'a ExtractorOptions,
+    handle: H,
+) -> Vec<(&'a [u8], usize, ExtractedKind)>
+where
+    H: Fn(Extractor<'a>) -> Vec<Extracted<'a>>,
+{
+    let start = blob.as_ptr() as usize;
+
+    blob.split(|x| *x == b'\n')
+        .filter(|line| !line.is_empty())
+        .flat_map(|line| handle(Extractor::new_with_options(line, options)))
+        .map(|x| {
+            let kind = x.kind();
+            match x {
+                Extracted::Candidate(bytes) | Extracted::CssVariable(bytes) => {
+                    (bytes, bytes.as_ptr() as usize - start, kind)
+                }
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 enum WalkEntry {
     Dir(PathBuf),
     File {
         path: PathBuf,
         mtime: Option<SystemTime>,
+        size: u64,
 
         /// Whether the path itself is a symlink
         is_symlink: bool,
@@ -641,10 +1849,13 @@ impl From<ignore::DirEntry> for WalkEntry {
         if is_dir {
             WalkEntry::Dir(path)
         } else {
-            let mtime = path.metadata().ok().and_then(|m| m.modified().ok());
+            let metadata = path.metadata().ok();
+            let mtime = metadata.as_ref().and_then(|m| m.modified().ok());
+            let size = metadata.map(|m| m.len()).unwrap_or_default();
             WalkEntry::File {
                 path,
                 mtime,
+                size,
                 is_symlink,
             }
         }
@@ -654,37 +1865,53 @@ impl From<ignore::DirEntry> for WalkEntry {
 /// Walk the file system synchronously. Used for the initial build where the overhead of spawning
 /// parallel walker threads is not worth it.
 #[tracing::instrument(skip_all)]
-fn walk_synchronous(walker: &mut WalkBuilder) -> Vec<WalkEntry> {
-    walker
-        .build()
-        .filter_map(Result::ok)
-        .map(WalkEntry::from)
-        .collect()
+fn walk_synchronous(walker: &mut WalkBuilder, sender: SyncSender<Vec<WalkEntry>>) {
+    let mut batch = Vec::with_capacity(WALK_BATCH_SIZE);
+
+    for entry in walker.build().filter_map(Result::ok) {
+        batch.push(WalkEntry::from(entry));
+
+        if batch.len() >= WALK_BATCH_SIZE {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(WALK_BATCH_SIZE));
+            if sender.send(full).is_err() {
+                return;
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        _ = sender.send(batch);
+    }
 }
 
 /// Walk the file system in parallel. Used in watch mode where the parallel walker overhead is
 /// amortised across many rebuilds and subsequent calls are much faster.
 #[tracing::instrument(skip_all)]
-fn walk_parallel(walker: &mut WalkBuilder) -> Vec<WalkEntry> {
+fn walk_parallel(walker: &mut WalkBuilder, sender: SyncSender<Vec<WalkEntry>>) {
     struct FlushOnDrop {
         local: Vec<WalkEntry>,
-        shared: Arc<Mutex<Vec<WalkEntry>>>,
+        sender: SyncSender<Vec<WalkEntry>>,
+    }
+
+    impl FlushOnDrop {
+        fn flush(&mut self) -> bool {
+            let full = std::mem::replace(&mut self.local, Vec::with_capacity(WALK_BATCH_SIZE));
+            self.sender.send(full).is_ok()
+        }
     }
 
     impl Drop for FlushOnDrop {
         fn drop(&mut self) {
             if !self.local.is_empty() {
-                self.shared.lock().unwrap().append(&mut self.local);
+                self.flush();
             }
         }
     }
 
-    let collected: Arc<Mutex<Vec<WalkEntry>>> = Arc::new(Mutex::new(vec![]));
-
     walker.build_parallel().run(|| {
         let mut buf = FlushOnDrop {
-            local: Vec::with_capacity(256),
-            shared: collected.clone(),
+            local: Vec::with_capacity(WALK_BATCH_SIZE),
+            sender: sender.clone(),
         };
 
         Box::new(move |entry| {
@@ -694,23 +1921,82 @@ fn walk_parallel(walker: &mut WalkBuilder) -> Vec<WalkEntry> {
 
             buf.local.push(WalkEntry::from(entry));
 
-            if buf.local.len() >= 256 {
-                buf.shared.lock().unwrap().append(&mut buf.local);
+            if buf.local.len() >= WALK_BATCH_SIZE && !buf.flush() {
+                return ignore::WalkState::Quit;
             }
 
             ignore::WalkState::Continue
         })
     });
-
-    // All threads have finished and flushed their buffers via FlushOnDrop::drop
-    Arc::try_unwrap(collected).unwrap().into_inner().unwrap()
 }
 
 /// Sets up a WalkBuilder with all source roots, gitignore rules, and source pattern matching.
 ///
+/// Verifies that files match any of the provided source patterns. This is necessary for manual
+/// patterns that can filter the file extension, which can't be expressed as ignore rules.
+#[derive(Debug, Clone, Default)]
+struct SourceFilter {
+    /// Bases of auto source detection and external sources, every file inside is allowed
+    auto_bases: Vec<PathBuf>,
+
+    /// Bases and patterns of `@source` patterns
+    pattern_sources: Vec<(PathBuf, String)>,
+}
+
+impl SourceFilter {
+    // Pre-compute source matching data to avoid allocations in the hot filter_entry path
+    fn new(sources: &Sources) -> Self {
+        let auto_bases: Vec<PathBuf> = sources
+            .iter()
+            .filter_map(|source| match source {
+                SourceEntry::Auto { base } | SourceEntry::External { base } => Some(base.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let pattern_sources: Vec<(PathBuf, String)> = sources
+            .iter()
+            .filter_map(|source| match source {
+                SourceEntry::Pattern { base, pattern } => Some((base.into(), pattern.into())),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            auto_bases,
+            pattern_sources,
+        }
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        if self.auto_bases.iter().any(|base| path.starts_with(base)) {
+            return true;
+        }
+
+        self.pattern_sources
+            .iter()
+            .any(|(base, pattern)| matches_source_pattern(base, pattern, path))
+    }
+}
+
+/// Whether `path` matches the `@source` `pattern` relative to `base`.
+fn matches_source_pattern(base: &Path, pattern: &str, path: &Path) -> bool {
+    let remainder = path.strip_prefix(base);
+    remainder.is_ok_and(|remainder| {
+        let mut path_str = remainder.to_string_lossy().to_string();
+        if !path_str.starts_with("/") {
+            path_str = format!("/{path_str}");
+        }
+        glob_match(pattern, path_str.as_bytes())
+    })
+}
+
 /// This is the common setup shared between the full walker (with mtime tracking for re-scans)
 /// and the parallel walker (without mtime tracking for the initial scan).
-fn create_walker(sources: &Sources) -> Option<WalkBuilder> {
+fn create_walker(
+    sources: &Sources,
+    auto_source_detection: &AutoSourceDetection,
+) -> Option<WalkBuilder> {
     let mut other_roots: FxHashSet<&PathBuf> = FxHashSet::default();
     let mut first_root: Option<&PathBuf> = None;
 
@@ -768,7 +2054,9 @@ fn create_walker(sources: &Sources) -> Option<WalkBuilder> {
                 emit(base, "!/**/*".to_owned());
 
                 // External sources should still disallow binary extensions:
-                emit(base, BINARY_EXTENSIONS_GLOB.clone());
+                if let Some(glob) = &auto_source_detection.binary_extensions_glob {
+                    emit(base, glob.clone());
+                }
             }
         }
     }
@@ -831,7 +2119,7 @@ fn create_walker(sources: &Sources) -> Option<WalkBuilder> {
     }
 
     // Setup auto source detection rules
-    for ignore in auto_source_detection::RULES.iter() {
+    for ignore in auto_source_detection.rules.iter() {
         builder.add_gitignore(ignore.clone());
     }
 
@@ -845,61 +2133,14 @@ fn create_walker(sources: &Sources) -> Option<WalkBuilder> {
         builder.add_gitignore(ignore);
     }
 
-    // Pre-compute source matching data to avoid allocations in the hot filter_entry path
-    let auto_bases: Vec<PathBuf> = sources
-        .iter()
-        .filter_map(|source| match source {
-            SourceEntry::Auto { base } | SourceEntry::External { base } => Some(base.clone()),
-            _ => None,
-        })
-        .collect();
-
-    let pattern_sources: Vec<(PathBuf, String)> = sources
-        .iter()
-        .filter_map(|source| match source {
-            SourceEntry::Pattern { base, pattern } => Some((base.into(), pattern.into())),
-            _ => None,
-        })
-        .collect();
-
     // Source pattern matching filter (lock-free, safe for parallel walking)
+    let source_filter = SourceFilter::new(sources);
     builder.filter_entry(move |entry| {
         let path = entry.path();
 
         // Ensure the entries are matching any of the provided source patterns (this is
         // necessary for manual-patterns that can filter the file extension)
-        if path.is_file() {
-            let mut matches = false;
-
-            for base in &auto_bases {
-                if path.starts_with(base) {
-                    matches = true;
-                    break;
-                }
-            }
-
-            if !matches {
-                for (base, pattern) in &pattern_sources {
-                    let remainder = path.strip_prefix(base);
-                    if remainder.is_ok_and(|remainder| {
-                        let mut path_str = remainder.to_string_lossy().to_string();
-                        if !path_str.starts_with("/") {
-                            path_str = format!("/{path_str}");
-                        }
-                        glob_match(pattern, path_str.as_bytes())
-                    }) {
-                        matches = true;
-                        break;
-                    }
-                }
-            }
-
-            if !matches {
-                return false;
-            }
-        }
-
-        true
+        !path.is_file() || source_filter.matches(path)
     });
 
     Some(builder)
@@ -907,7 +2148,7 @@ fn create_walker(sources: &Sources) -> Option<WalkBuilder> {
 
 #[cfg(test)]
 mod tests {
-    use super::{ChangedContent, Scanner};
+    use super::{ChangedContent, ExtractedKind, Scanner, ScannerOptions};
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -942,11 +2183,120 @@ mod tests {
                 ],
             ),
         ] {
-            let candidates = scanner.get_candidates_with_positions(ChangedContent::Content(
+            let candidates = scanner
+                .get_candidates_with_positions(ChangedContent::Content(
+                    input.to_string(),
+                    "html".into(),
+                ))
+                .into_iter()
+                .map(|(candidate, position, kind)| {
+                    assert_eq!(kind, ExtractedKind::Candidate);
+                    (candidate, position)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(candidates, expected);
+        }
+    }
+
+    #[test]
+    fn test_positions_agree_with_scanned_candidates() {
+        for input in [
+            "<div class=\"flex\nunderline\">",
+            "{\n  'flex': true,\n  'p-4': false\n}",
+            // A candidate at the end of a line is only extracted when lines are split
+            "a <p-4:\nb",
+        ] {
+            let mut scanner = Scanner::new(vec![]);
+            let mut positions = scanner
+                .get_candidates_with_positions(ChangedContent::Content(
+                    input.to_string(),
+                    "html".into(),
+                ))
+                .into_iter()
+                .map(|(candidate, _, _)| candidate)
+                .collect::<Vec<_>>();
+            positions.sort();
+            positions.dedup();
+
+            let mut candidates = scanner.scan_content(vec![ChangedContent::Content(
                 input.to_string(),
                 "html".into(),
-            ));
-            assert_eq!(candidates, expected);
+            )]);
+            candidates.sort();
+
+            assert_eq!(positions, candidates, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_css_variable_positions() {
+        let mut scanner = Scanner::new(vec![]);
+
+        let positions = scanner.get_candidates_with_positions(ChangedContent::Content(
+            r#"<div class="flex" style="color: var(--brand-color)"></div>"#.to_string(),
+            "html".into(),
+        ));
+
+        assert!(positions.contains(&("flex".to_string(), 12, ExtractedKind::Candidate)));
+        assert!(positions.contains(&("--brand-color".to_string(), 36, ExtractedKind::CssVariable)));
+    }
+
+    #[test]
+    fn test_positions_with_prefix() {
+        let mut scanner = Scanner::new_with_options(
+            vec![],
+            ScannerOptions {
+                prefix: Some("tw".into()),
+                ..Default::default()
+            },
+        );
+
+        let positions = scanner.get_candidates_with_positions(ChangedContent::Content(
+            r#"<div class="tw:flex! tw:sm:block! flex tw:[color:red] tw:group-[]:flex" style="color: var(--tw-color)"></div>"#.to_string(),
+            "html".into(),
+        ));
+
+        assert_eq!(
+            positions,
+            vec![
+                ("--tw-color".to_string(), 90, ExtractedKind::CssVariable),
+                ("tw:flex!".to_string(), 12, ExtractedKind::Candidate),
+                ("tw:sm:block!".to_string(), 21, ExtractedKind::Candidate),
+                ("tw:[color:red]".to_string(), 39, ExtractedKind::Candidate),
+                ("tw:group-[]:flex".to_string(), 54, ExtractedKind::Candidate),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_positions_point_to_the_original_content() {
+        let mut scanner = Scanner::new(vec![]);
+
+        for (extension, input) in [
+            (
+                "vue",
+                "<template lang=\"pug\">\n.bg-red-500.p-4 Hello\n</template>\n<div class=\"flex\"></div>",
+            ),
+            ("pug", "div.flex.items-center(class=\"p-4\")"),
+            ("haml", "%div.flex.p-4\n  = link_to 'Home', class: 'underline'"),
+            (
+                "rb",
+                "html = <<~HTML\n  <div class=\"flex\">\n  </div>\nHTML\n%w[p-4 underline]",
+            ),
+            ("slim", "div.flex.p-4 Hello"),
+            ("svelte", "<div class:px-4={active} class=\"flex\">"),
+            ("clj", "[:div.flex.p-4 {:class \"underline\"}]"),
+            ("md", "# Title {.underline .p-4}"),
+        ] {
+            for (candidate, offset, _) in scanner.get_candidates_with_positions(
+                ChangedContent::Content(input.to_string(), extension.into()),
+            ) {
+                assert_eq!(
+                    &input[offset..offset + candidate.len()],
+                    candidate,
+                    "Wrong offset in `{extension}` content"
+                );
+            }
         }
     }
 }
@@ -0,0 +1,298 @@
+use crate::extractor::pre_processors::{BuiltinPreProcessor, PreProcessor};
+use fast_glob::glob_match;
+use fxhash::FxHashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{self, Arc};
+
+/// Routes files to a built-in pre-processor, e.g.: `.view` files to `BuiltinPreProcessor::Vue`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreProcessorMapping {
+    /// A file extension without the leading `.` (`view`, `blade.php`), or a glob. Globs without a
+    /// `/` are matched against the file name (`*.liquid.html`), other globs against the full path
+    /// (`**/templates/*.html`).
+    pub pattern: String,
+
+    pub pre_processor: BuiltinPreProcessor,
+}
+
+/// The pre-processors of a scanner, keyed by extension. The built-in pre-processors are
+/// registered by default, and can be overridden.
+///
+/// Globs are checked first, in the order they were defined. Then the extensions, longest first,
+/// such that `blade.php` wins over `php`. Files that match neither are not pre-processed.
+///
+/// Only whole files are routed through the registry. Languages embedded in another file, e.g.: a
+/// `<template lang="pug">` inside a `.vue` file, always use the built-in pre-processors.
+#[derive(Clone)]
+pub struct PreProcessorRegistry {
+    globs: Vec<(String, Arc<dyn PreProcessor>)>,
+
+    /// Extensions without the leading `.`, e.g.: `blade.php`
+    extensions: FxHashMap<String, Arc<dyn PreProcessor>>,
+}
+
+static DEFAULT: sync::LazyLock<PreProcessorRegistry> =
+    sync::LazyLock::new(|| PreProcessorRegistry {
+        globs: vec![],
+        extensions: BuiltinPreProcessor::DEFAULTS
+            .iter()
+            .map(|(extension, pre_processor)| {
+                (
+                    extension.to_string(),
+                    Arc::new(*pre_processor) as Arc<dyn PreProcessor>,
+                )
+            })
+            .collect(),
+    });
+
+impl Default for PreProcessorRegistry {
+    fn default() -> Self {
+        DEFAULT.clone()
+    }
+}
+
+impl fmt::Debug for PreProcessorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut extensions = self.extensions.keys().collect::<Vec<_>>();
+        extensions.sort_unstable();
+
+        f.debug_struct("PreProcessorRegistry")
+            .field(
+                "globs",
+                &self.globs.iter().map(|(glob, _)| glob).collect::<Vec<_>>(),
+            )
+            .field("extensions", &extensions)
+            .finish()
+    }
+}
+
+impl PreProcessorRegistry {
+    /// The built-in pre-processors, with the given mappings applied on top.
+    pub fn new(mappings: &[PreProcessorMapping]) -> Self {
+        let mut registry = Self::default();
+        for mapping in mappings {
+            registry.register(&mapping.pattern, Box::new(mapping.pre_processor));
+        }
+
+        registry
+    }
+
+    /// Use `pre_processor` for all files with the given extension or glob, replacing whatever was
+    /// registered for it before. See `PreProcessorMapping::pattern` for how the pattern is matched.
+    pub fn register(&mut self, pattern: &str, pre_processor: Box<dyn PreProcessor>) {
+        let pre_processor: Arc<dyn PreProcessor> = pre_processor.into();
+
+        if is_glob(pattern) {
+            match self.globs.iter_mut().find(|(glob, _)| glob == pattern) {
+                Some((_, existing)) => *existing = pre_processor,
+                None => self.globs.push((pattern.to_owned(), pre_processor)),
+            }
+        } else {
+            self.extensions
+                .insert(pattern.trim_start_matches('.').to_owned(), pre_processor);
+        }
+    }
+
+    /// Stop pre-processing files with the given extension or glob.
+    pub fn unregister(&mut self, pattern: &str) {
+        if is_glob(pattern) {
+            self.globs.retain(|(glob, _)| glob != pattern);
+        } else {
+            self.extensions.remove(pattern.trim_start_matches('.'));
+        }
+    }
+
+    /// The pre-processor for a file, or for raw content with the given extension when there is no
+    /// file.
+    ///
+    /// Globs win over extensions, and the extensions of the file name win over `extension`, which
+    /// is only used for a file when nothing else matches it.
+    pub fn resolve(&self, path: Option<&Path>, extension: &str) -> Option<&dyn PreProcessor> {
+        let file_name = path
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string());
+
+        if let Some(path) = path {
+            let full_path = path.to_string_lossy();
+            let file_name = file_name.as_deref().unwrap_or_default();
+
+            for (glob, pre_processor) in &self.globs {
+                let subject = if glob.contains('/') {
+                    full_path.as_ref()
+                } else {
+                    file_name
+                };
+
+                if glob_match(glob, subject.as_bytes()) {
+                    return Some(pre_processor.as_ref());
+                }
+            }
+        }
+
+        // Try every extension of the file, longest first: `blade.php`, then `php`
+        let by_extension = |name: &str| {
+            name.match_indices('.').find_map(|(idx, _)| {
+                self.extensions
+                    .get(&name[idx + 1..])
+                    .map(|pre_processor| pre_processor.as_ref())
+            })
+        };
+
+        file_name
+            .as_deref()
+            .and_then(by_extension)
+            .or_else(|| by_extension(&format!(".{extension}")))
+    }
+
+    /// Describes every registered pattern and pre-processor, or `None` when any of the
+    /// pre-processors doesn't have a `cache_key`.
+    pub fn cache_key(&self) -> Option<String> {
+        let mut extensions = self
+            .extensions
+            .iter()
+            .map(|(extension, pre_processor)| {
+                Some(format!("{extension}={}", pre_processor.cache_key()?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        extensions.sort_unstable();
+
+        let globs = self
+            .globs
+            .iter()
+            .map(|(glob, pre_processor)| Some(format!("{glob}={}", pre_processor.cache_key()?)))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some([globs, extensions].concat().join("\n"))
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{', '/'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PreProcessorMapping, PreProcessorRegistry};
+    use crate::extractor::pre_processors::{
+        process_checking_offsets, BuiltinPreProcessor, PreProcessor,
+    };
+    use std::path::Path;
+
+    fn process(
+        registry: &PreProcessorRegistry,
+        path: Option<&Path>,
+        extension: &str,
+        content: Vec<u8>,
+    ) -> Vec<u8> {
+        match registry.resolve(path, extension) {
+            Some(pre_processor) => process_checking_offsets(pre_processor, &content),
+            None => content,
+        }
+    }
+
+    /// Writes its name over the start of the content, to see which pre-processor was used
+    struct Named(&'static str);
+
+    impl PreProcessor for Named {
+        fn process(&self, content: &[u8]) -> Vec<u8> {
+            let mut result = content.to_vec();
+            result[..self.0.len()].copy_from_slice(self.0.as_bytes());
+            result
+        }
+    }
+
+    /// Breaks the offsets of everything that follows
+    struct Expand;
+
+    impl PreProcessor for Expand {
+        fn process(&self, content: &[u8]) -> Vec<u8> {
+            content.repeat(2)
+        }
+    }
+
+    #[test]
+    fn test_resolve_pre_processors() {
+        let mut registry = PreProcessorRegistry::default();
+        for (pattern, name) in [
+            ("php", "php"),
+            (".blade.php", "blade"),
+            ("view", "view"),
+            ("vue", "vue"),
+            ("**/emails/*.html", "emails"),
+            ("*.liquid.html", "liquid"),
+        ] {
+            registry.register(pattern, Box::new(Named(name)));
+        }
+
+        let resolve = |registry: &PreProcessorRegistry, path: &str, extension: &str| {
+            // Pre-processors are not allowed to write over whitespace
+            let content = format!("{:_<16}", "raw").into_bytes();
+            let content = process(registry, Some(Path::new(path)), extension, content);
+            String::from_utf8(content)
+                .unwrap()
+                .trim_end_matches('_')
+                .to_owned()
+        };
+
+        assert_eq!(resolve(&registry, "/app/index.blade.php", "php"), "blade");
+        assert_eq!(resolve(&registry, "/app/index.php", "php"), "php");
+        assert_eq!(resolve(&registry, "/app/Card.view", "view"), "view");
+        assert_eq!(
+            resolve(&registry, "/app/page.liquid.html", "html"),
+            "liquid"
+        );
+        assert_eq!(
+            resolve(&registry, "/app/emails/page.liquid.html", "html"),
+            "emails"
+        );
+
+        // Files without a pre-processor are left as-is
+        assert_eq!(resolve(&registry, "/app/index.html", "html"), "raw");
+
+        // The given extension is used when nothing matches the file itself
+        assert_eq!(resolve(&registry, "/app/x.html", "vue"), "vue");
+        assert_eq!(resolve(&registry, "/app/index.php", "vue"), "php");
+
+        // Raw content only has an extension
+        let content = process(&registry, None, "blade.php", b"raw___".to_vec());
+        assert_eq!(content, b"blade_");
+
+        // Built-in pre-processors can be overridden, or removed
+        assert!(registry.resolve(None, "svelte").is_some());
+        registry.register("svelte", Box::new(Named("svelte")));
+        assert_eq!(resolve(&registry, "/app/App.svelte", "svelte"), "svelte");
+        registry.unregister("svelte");
+        assert_eq!(resolve(&registry, "/app/App.svelte", "svelte"), "raw");
+    }
+
+    #[test]
+    fn test_output_with_a_different_length_is_kept() {
+        let mut registry = PreProcessorRegistry::default();
+        registry.register("php", Box::new(Expand));
+
+        let content = process(
+            &registry,
+            Some(Path::new("/app/index.php")),
+            "php",
+            b"raw".to_vec(),
+        );
+        assert_eq!(content, b"rawraw");
+    }
+
+    #[test]
+    fn test_pre_processor_mappings() {
+        let registry = PreProcessorRegistry::new(&[PreProcessorMapping {
+            pattern: "view".into(),
+            pre_processor: BuiltinPreProcessor::Svelte,
+        }]);
+
+        let content = process(
+            &registry,
+            Some(Path::new("/app/Card.view")),
+            "view",
+            b"<div class:flex={active}>".to_vec(),
+        );
+        assert_eq!(content, b"<div class flex={active}>");
+    }
+}
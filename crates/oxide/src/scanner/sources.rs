@@ -5,8 +5,6 @@ use ignore::gitignore::Gitignore;
 use std::path::{Component, Path, PathBuf};
 use tracing::{event, Level};
 
-use super::auto_source_detection::IGNORED_CONTENT_DIRS;
-
 #[derive(Debug, Clone)]
 pub struct PublicSourceEntry {
     /// Base path of the glob
@@ -381,10 +379,52 @@ fn path_to_posix_string(path: &Path) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scanner::auto_source_detection::{
+        AutoSourceDetection, AutoSourceDetectionOptions, RuleListChanges,
+    };
     use pretty_assertions::assert_eq;
     use std::fs;
     use tempfile::tempdir;
 
+    fn to_private_source_entries(sources: Vec<PublicSourceEntry>) -> Vec<SourceEntry> {
+        public_source_entries_to_private_source_entries(
+            sources,
+            &AutoSourceDetection::default().ignored_content_dirs,
+        )
+    }
+
+    #[test]
+    fn sources_inside_ignored_content_dirs_are_external() {
+        let source = |base: &str| PublicSourceEntry {
+            base: base.replace('/', std::path::MAIN_SEPARATOR_STR),
+            pattern: "/**/*".into(),
+            negated: false,
+        };
+        let is_external = |base: &str, options: &AutoSourceDetectionOptions| {
+            let rules = AutoSourceDetection::new(options);
+            matches!(
+                SourceEntry::from_public(source(base), &rules.ignored_content_dirs),
+                SourceEntry::External { .. }
+            )
+        };
+
+        let defaults = AutoSourceDetectionOptions::default();
+        assert!(is_external("/project/.venv", &defaults));
+        assert!(is_external("/project/node_modules/my-ui-lib", &defaults));
+        assert!(!is_external("/project/dist-storybook", &defaults));
+
+        let options = AutoSourceDetectionOptions {
+            ignored_content_dirs: RuleListChanges {
+                add: vec!["dist-storybook".into()],
+                remove: vec![".venv".into()],
+            },
+            ..Default::default()
+        };
+        assert!(!is_external("/project/.venv", &options));
+        assert!(is_external("/project/node_modules/my-ui-lib", &options));
+        assert!(is_external("/project/dist-storybook", &options));
+    }
+
     #[test]
     fn path_to_posix_string_serializes_relative_paths() {
         let path = PathBuf::from("src").join("**").join("*.html");
@@ -479,7 +519,7 @@ mod tests {
         fs::create_dir_all(dir.path().join("src")).unwrap();
         let base = dunce::canonicalize(dir.path().join("src")).unwrap();
 
-        let sources = public_source_entries_to_private_source_entries(vec![PublicSourceEntry {
+        let sources = to_private_source_entries(vec![PublicSourceEntry {
             base: dir.path().to_string_lossy().to_string(),
             pattern: "src/foo.html".to_string(),
             negated: false,
@@ -506,7 +546,7 @@ mod tests {
         fs::create_dir_all(dir.path().join("src")).unwrap();
         let base = dunce::canonicalize(dir.path().join("src")).unwrap();
 
-        let sources = public_source_entries_to_private_source_entries(vec![PublicSourceEntry {
+        let sources = to_private_source_entries(vec![PublicSourceEntry {
             base: dir.path().to_string_lossy().to_string(),
             pattern: "src/ef*/*.html".to_string(),
             negated: false,
@@ -537,7 +577,7 @@ mod tests {
         fs::create_dir_all(dir.path().join("src")).unwrap();
         let base = dunce::canonicalize(dir.path().join("src")).unwrap();
 
-        let sources = public_source_entries_to_private_source_entries(vec![
+        let sources = to_private_source_entries(vec![
             PublicSourceEntry {
                 base: dir.path().to_string_lossy().to_string(),
                 pattern: "src".to_string(),
@@ -571,7 +611,7 @@ mod tests {
         let users = dunce::canonicalize(dir.path().join("Users")).unwrap();
         let project = dunce::canonicalize(project).unwrap();
 
-        let sources = public_source_entries_to_private_source_entries(vec![
+        let sources = to_private_source_entries(vec![
             PublicSourceEntry {
                 base: project.to_string_lossy().to_string(),
                 pattern: "**/*".to_string(),
@@ -608,7 +648,7 @@ mod tests {
         fs::create_dir_all(dir.path().join("src")).unwrap();
         let base = dunce::canonicalize(dir.path().join("src")).unwrap();
 
-        let sources = public_source_entries_to_private_source_entries(vec![
+        let sources = to_private_source_entries(vec![
             PublicSourceEntry {
                 base: dir.path().to_string_lossy().to_string(),
                 pattern: "src/foo.html".to_string(),
@@ -643,7 +683,7 @@ mod tests {
     /// Run the public-to-private conversion for an auto-detected source pointing at `base` and
     /// return the resulting entry.
     fn auto_source_entry(base: &Path) -> SourceEntry {
-        public_source_entries_to_private_source_entries(vec![PublicSourceEntry {
+        to_private_source_entries(vec![PublicSourceEntry {
             base: base.to_string_lossy().to_string(),
             pattern: "**/*".to_string(),
             negated: false,
@@ -734,8 +774,10 @@ mod tests {
 ///
 /// 3. Convert to private SourceEntry
 ///
+/// Auto sources inside any of the `ignored_content_dirs` become external sources.
 pub fn public_source_entries_to_private_source_entries(
     sources: Vec<PublicSourceEntry>,
+    ignored_content_dirs: &[String],
 ) -> Vec<SourceEntry> {
     // Perform brace expansion
     let expanded_globs = sources
@@ -776,7 +818,7 @@ pub fn public_source_entries_to_private_source_entries(
     let sources = expanded_globs
         .into_iter()
         .map(|public_source| {
-            let mut source: SourceEntry = public_source.into();
+            let mut source = SourceEntry::from_public(public_source, ignored_content_dirs);
 
             // Promote auto-sources to external sources if they were gitignored
             if let SourceEntry::Auto { ref base } = source {
@@ -848,9 +890,10 @@ pub fn public_source_entries_to_private_source_entries(
     expand_restricted_patterns(sources)
 }
 
-/// Convert a public source entry to a source entry
-impl From<PublicSourceEntry> for SourceEntry {
-    fn from(value: PublicSourceEntry) -> Self {
+impl SourceEntry {
+    /// Convert a public source entry to a source entry. Auto sources inside any of the
+    /// `ignored_content_dirs` become external sources.
+    pub fn from_public(value: PublicSourceEntry, ignored_content_dirs: &[String]) -> Self {
         if value.negated {
             return SourceEntry::Ignored {
                 base: value.base.into(),
@@ -868,7 +911,7 @@ impl From<PublicSourceEntry> for SourceEntry {
             };
         }
 
-        let inside_ignored_content_dir = IGNORED_CONTENT_DIRS.iter().any(|dir| {
+        let inside_ignored_content_dir = ignored_content_dirs.iter().any(|dir| {
             value.base.contains(&format!(
                 "{}{}{}",
                 std::path::MAIN_SEPARATOR,
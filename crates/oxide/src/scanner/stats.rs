@@ -0,0 +1,134 @@
+use crate::scanner::limits::SkipReason;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Numbers about the last `scan()`, `scan_diff()`, `scan_content()` or `scan_content_diff()`
+/// call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Files that were read during the scan, but skipped because they look binary
+    pub binary_files: Vec<String>,
+
+    /// Wall clock time of the whole scan
+    pub duration: Duration,
+
+    /// Walking the file system to find the files to scan
+    pub walking: PhaseStats,
+
+    /// Reading files from disk
+    pub reading: PhaseStats,
+
+    /// Pre-processing files that have a pre-processor, e.g.: `.svelte` or `.pug` files
+    pub pre_processing: PhaseStats,
+
+    /// Extracting candidates, including raw content
+    pub extraction: PhaseStats,
+}
+
+/// How long a single phase of a scan took, and how much it handled.
+///
+/// Files are read, pre-processed and extracted on multiple threads at the same time, so the
+/// durations of these phases are summed over all threads and can exceed the `duration` of the
+/// scan.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseStats {
+    pub duration: Duration,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Collects the `PhaseStats` of a single phase from multiple threads.
+#[derive(Debug, Default)]
+pub struct PhaseCounter {
+    nanos: AtomicU64,
+    files: AtomicUsize,
+    bytes: AtomicU64,
+}
+
+impl PhaseCounter {
+    /// Count a file of `bytes` bytes that was handled since `started`.
+    pub fn record(&self, started: Instant, bytes: u64) {
+        self.add_time(started);
+        self.add_file(bytes);
+    }
+
+    pub fn add_time(&self, started: Instant) {
+        let nanos = started.elapsed().as_nanos().try_into().unwrap_or(u64::MAX);
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub fn add_file(&self, bytes: u64) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> PhaseStats {
+        PhaseStats {
+            duration: Duration::from_nanos(self.nanos.load(Ordering::Relaxed)),
+            files: self.files.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The counters of every phase of a scan.
+#[derive(Debug, Default)]
+pub struct PhaseCounters {
+    pub walking: PhaseCounter,
+    pub reading: PhaseCounter,
+    pub pre_processing: PhaseCounter,
+    pub extraction: PhaseCounter,
+}
+
+impl ScanStats {
+    pub fn record_skipped(&mut self, path: &Path, reason: SkipReason) {
+        if reason == SkipReason::Binary {
+            if let Some(path) = path.to_str() {
+                self.binary_files.push(path.to_owned());
+            }
+        }
+    }
+
+    /// Wrap up the scan that started at `started`.
+    pub fn finish(&mut self, started: Instant, counters: &PhaseCounters) {
+        self.binary_files.sort_unstable();
+
+        self.duration = started.elapsed();
+        self.walking = counters.walking.stats();
+        self.reading = counters.reading.stats();
+        self.pre_processing = counters.pre_processing.stats();
+        self.extraction = counters.extraction.stats();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PhaseCounters, ScanStats};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_phase_stats() {
+        let started = Instant::now();
+        let counters = PhaseCounters::default();
+
+        std::thread::scope(|scope| {
+            for bytes in [10, 20, 30] {
+                let counters = &counters;
+                scope.spawn(move || counters.reading.record(Instant::now(), bytes));
+            }
+        });
+        counters.walking.add_file(5);
+        std::thread::sleep(Duration::from_millis(1));
+        counters.walking.add_time(started);
+
+        let mut stats = ScanStats::default();
+        stats.finish(started, &counters);
+
+        assert_eq!((stats.reading.files, stats.reading.bytes), (3, 60));
+        assert_eq!((stats.walking.files, stats.walking.bytes), (1, 5));
+        assert!(stats.walking.duration >= Duration::from_millis(1));
+        assert!(stats.duration >= stats.walking.duration);
+        assert_eq!(stats.extraction, Default::default());
+    }
+}
@@ -0,0 +1,216 @@
+use fxhash::FxHashMap;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// How often every candidate occurs in a single file, or a single piece of raw content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentUsage {
+    /// The extension of the file or content, without the leading `.`, e.g.: `html`
+    pub extension: String,
+
+    pub counts: FxHashMap<String, usize>,
+}
+
+/// Keeps track of how often candidates occur in every scanned file, only used when usage
+/// statistics are enabled.
+///
+/// Like the `CandidateIndex`, raw content (`ChangedContent::Content`) doesn't belong to a file and
+/// is kept forever.
+#[derive(Debug, Clone, Default)]
+pub struct UsageIndex {
+    /// Usage of each scanned file, keyed by the canonical path of the file
+    files: FxHashMap<PathBuf, ContentUsage>,
+
+    /// Usage of all raw content, per extension
+    content: FxHashMap<String, FxHashMap<String, usize>>,
+}
+
+impl UsageIndex {
+    /// Replace the usage of `file`. Files without usage, e.g.: skipped files, are forgotten.
+    pub fn update_file(&mut self, file: PathBuf, usage: Option<ContentUsage>) {
+        match usage {
+            Some(usage) => self.files.insert(file, usage),
+            None => self.files.remove(&file),
+        };
+    }
+
+    pub fn add_content(&mut self, usage: ContentUsage) {
+        let counts = self.content.entry(usage.extension).or_default();
+        for (candidate, count) in usage.counts {
+            *counts.entry(candidate).or_default() += count;
+        }
+    }
+
+    /// Forget about all files that don't pass the `keep` check.
+    pub fn retain_files<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&Path) -> bool,
+    {
+        self.files.retain(|file, _| keep(file));
+    }
+
+    pub fn report(&self) -> UsageReport {
+        let mut candidates: FxHashMap<&str, CandidateUsage> = FxHashMap::default();
+        let mut extensions: FxHashMap<&str, ExtensionUsage> = FxHashMap::default();
+
+        let files = self
+            .files
+            .values()
+            .map(|usage| (&usage.extension, &usage.counts, true));
+        let content = self
+            .content
+            .iter()
+            .map(|(extension, counts)| (extension, counts, false));
+
+        for (extension, counts, is_file) in files.chain(content) {
+            let extension_usage = extensions
+                .entry(extension)
+                .or_insert_with(|| ExtensionUsage {
+                    extension: extension.clone(),
+                    ..Default::default()
+                });
+            extension_usage.files += usize::from(is_file);
+            extension_usage.candidates += counts.len();
+
+            for (candidate, count) in counts {
+                extension_usage.occurrences += count;
+
+                let candidate_usage =
+                    candidates
+                        .entry(candidate)
+                        .or_insert_with(|| CandidateUsage {
+                            candidate: candidate.clone(),
+                            ..Default::default()
+                        });
+                candidate_usage.occurrences += count;
+                candidate_usage.files += usize::from(is_file);
+            }
+        }
+
+        let mut candidates = candidates.into_values().collect::<Vec<_>>();
+        candidates.sort_unstable_by(|a, b| a.candidate.cmp(&b.candidate));
+
+        let mut extensions = extensions.into_values().collect::<Vec<_>>();
+        extensions.sort_unstable_by(|a, b| a.extension.cmp(&b.extension));
+
+        UsageReport {
+            candidates,
+            extensions,
+        }
+    }
+}
+
+/// How often candidates occur in all scanned files and content.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    /// Every candidate, sorted by name
+    pub candidates: Vec<CandidateUsage>,
+
+    /// Every extension, sorted by name
+    pub extensions: Vec<ExtensionUsage>,
+}
+
+impl UsageReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandidateUsage {
+    pub candidate: String,
+
+    /// How often the candidate occurs in all files and content
+    pub occurrences: usize,
+
+    /// The number of files the candidate occurs in
+    pub files: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionUsage {
+    /// The extension without the leading `.`, e.g.: `html`
+    pub extension: String,
+
+    /// The number of files with this extension
+    pub files: usize,
+
+    /// The number of unique candidates per file, summed over all files with this extension
+    pub candidates: usize,
+
+    /// How often candidates occur in all files with this extension
+    pub occurrences: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CandidateUsage, ContentUsage, ExtensionUsage, UsageIndex};
+    use pretty_assertions::assert_eq;
+    use std::path::{Path, PathBuf};
+
+    fn usage(extension: &str, counts: &[(&str, usize)]) -> ContentUsage {
+        ContentUsage {
+            extension: extension.into(),
+            counts: counts
+                .iter()
+                .map(|(candidate, count)| (candidate.to_string(), *count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_usage_report() {
+        let mut index = UsageIndex::default();
+        index.update_file(
+            PathBuf::from("a.html"),
+            Some(usage("html", &[("flex", 3), ("underline", 1)])),
+        );
+        index.update_file(PathBuf::from("b.html"), Some(usage("html", &[("flex", 1)])));
+        index.update_file(PathBuf::from("c.jsx"), Some(usage("jsx", &[("flex", 2)])));
+        index.update_file(PathBuf::from("d.vue"), Some(usage("vue", &[("block", 1)])));
+        index.add_content(usage("html", &[("flex", 1), ("block", 2)]));
+
+        // Updated, skipped and deleted files no longer count
+        index.update_file(PathBuf::from("b.html"), Some(usage("html", &[("flex", 2)])));
+        index.update_file(PathBuf::from("c.jsx"), None);
+        index.retain_files(|file| file != Path::new("d.vue"));
+
+        let report = index.report();
+        assert_eq!(
+            report.candidates,
+            vec![
+                CandidateUsage {
+                    candidate: "block".into(),
+                    occurrences: 2,
+                    files: 0,
+                },
+                CandidateUsage {
+                    candidate: "flex".into(),
+                    occurrences: 6,
+                    files: 2,
+                },
+                CandidateUsage {
+                    candidate: "underline".into(),
+                    occurrences: 1,
+                    files: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            report.extensions,
+            vec![ExtensionUsage {
+                extension: "html".into(),
+                files: 2,
+                candidates: 5,
+                occurrences: 9,
+            }]
+        );
+
+        assert!(report
+            .to_json()
+            .starts_with(r#"{"candidates":[{"candidate":"block","occurrences":2,"files":0}"#));
+    }
+}
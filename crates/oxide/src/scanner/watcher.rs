@@ -0,0 +1,308 @@
+use crate::scanner::{ChangedContent, ScanDiff, Scanner};
+use fxhash::FxHashSet;
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::event;
+
+/// Changes are held back for at most this many times the `debounce` window, even when the file
+/// system never gets quiet, e.g.: when a bundler keeps writing to a watched folder.
+const MAX_LATENCY_FACTOR: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// How long the file system has to be quiet before the collected changes are scanned. Changes
+    /// are scanned after at most 10 times this duration, even when the file system is never quiet.
+    pub debounce: Duration,
+
+    /// Poll the file system for changes instead of relying on native file system events. Polling
+    /// is also used when native file system events are unavailable. Note that polling compares
+    /// mtimes in whole seconds.
+    pub poll: bool,
+
+    /// How often to poll the file system when polling
+    pub poll_interval: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(50),
+            poll: false,
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+enum Message {
+    Event(notify::Result<notify::Event>),
+    Stop,
+}
+
+/// A running watcher, created by `Scanner::watch`. The scanner lives on a background thread until
+/// the watcher is stopped or dropped.
+pub struct ScannerWatcher {
+    sender: Sender<Message>,
+    thread: Option<JoinHandle<Scanner>>,
+}
+
+impl ScannerWatcher {
+    /// Stop watching, and hand back the scanner.
+    pub fn stop(mut self) -> Option<Scanner> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Option<Scanner> {
+        let thread = self.thread.take()?;
+        _ = self.sender.send(Message::Stop);
+        thread.join().ok()
+    }
+}
+
+impl Drop for ScannerWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Scanner {
+    /// Watch all sources for changes, and call `on_change` with the difference every time the
+    /// changes are scanned.
+    ///
+    /// Only the directories the walker visits are watched, which means that the same ignore rules
+    /// apply to watching as to scanning. If no scan happened yet, an initial scan is done first
+    /// and reported through `on_change` as well.
+    pub fn watch<F>(
+        mut self,
+        options: WatchOptions,
+        mut on_change: F,
+    ) -> notify::Result<ScannerWatcher>
+    where
+        F: FnMut(ScanDiff) + Send + 'static,
+    {
+        let initial = (!self.has_scanned_once).then(|| self.scan_diff());
+
+        let (sender, receiver) = channel();
+        let mut watcher = create_watcher(&options, &sender)?;
+
+        let mut watched = FxHashSet::default();
+        watch_dirs(watcher.as_mut(), &mut watched, &self.dirs);
+
+        let thread = std::thread::spawn(move || {
+            if let Some(diff) = initial.filter(|diff| !diff.is_empty()) {
+                on_change(diff);
+            }
+
+            while let Some(events) = next_batch(&receiver, options.debounce) {
+                let diff = self.apply_events(events);
+                watch_dirs(watcher.as_mut(), &mut watched, &self.dirs);
+
+                if !diff.is_empty() {
+                    on_change(diff);
+                }
+            }
+
+            drop(watcher);
+            self
+        });
+
+        Ok(ScannerWatcher {
+            sender,
+            thread: Some(thread),
+        })
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn apply_events(&mut self, events: Vec<notify::Event>) -> ScanDiff {
+        // Whether folders were created in a way that requires walking the sources again
+        let mut new_dirs = false;
+        let mut changed_files: FxHashSet<PathBuf> = FxHashSet::default();
+        let mut changed_content = vec![];
+
+        for event in events {
+            match event.kind {
+                EventKind::Access(_) => {}
+                EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                    changed_content.extend(event.paths.into_iter().map(ChangedContent::Removed));
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                    let [from, to] = <[PathBuf; 2]>::try_from(event.paths).unwrap();
+                    changed_content.push(ChangedContent::Removed(from));
+                    if to.is_dir() {
+                        new_dirs = true;
+                    } else {
+                        changed_files.insert(to);
+                    }
+                }
+                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                    for path in event.paths {
+                        if path.is_dir() {
+                            new_dirs = true;
+                        } else if path.is_file() {
+                            changed_files.insert(path);
+                        } else {
+                            changed_content.push(ChangedContent::Removed(path));
+                        }
+                    }
+                }
+                _ => {
+                    changed_files.extend(
+                        event
+                            .paths
+                            .into_iter()
+                            .filter(|path| self.files.contains(path)),
+                    );
+                }
+            }
+        }
+
+        if new_dirs {
+            event!(
+                tracing::Level::INFO,
+                "Rescanning sources after folders were added"
+            );
+            return self.scan_diff();
+        }
+
+        changed_content.extend(changed_files.into_iter().map(|path| {
+            let extension = path
+                .extension()
+                .and_then(|x| x.to_str())
+                .unwrap_or_default()
+                .to_owned();
+            ChangedContent::File(path, extension)
+        }));
+
+        if changed_content.is_empty() {
+            return ScanDiff::default();
+        }
+
+        self.scan_content_diff(changed_content)
+    }
+}
+
+fn create_watcher(
+    options: &WatchOptions,
+    sender: &Sender<Message>,
+) -> notify::Result<Box<dyn Watcher + Send>> {
+    let handler = |sender: Sender<Message>| {
+        move |event| {
+            _ = sender.send(Message::Event(event));
+        }
+    };
+
+    if !options.poll {
+        match RecommendedWatcher::new(handler(sender.clone()), notify::Config::default()) {
+            Ok(watcher) => return Ok(Box::new(watcher)),
+            Err(err) => {
+                event!(
+                    tracing::Level::WARN,
+                    "Falling back to polling, native file watching is unavailable: {}",
+                    err
+                );
+            }
+        }
+    }
+
+    Ok(Box::new(PollWatcher::new(
+        handler(sender.clone()),
+        notify::Config::default().with_poll_interval(options.poll_interval),
+    )?))
+}
+
+/// Ensure exactly the given `dirs` are watched.
+fn watch_dirs(
+    watcher: &mut (dyn Watcher + Send),
+    watched: &mut FxHashSet<PathBuf>,
+    dirs: &FxHashSet<PathBuf>,
+) {
+    watched.retain(|dir| {
+        if dirs.contains(dir) {
+            return true;
+        }
+
+        // The folder is most likely gone already
+        _ = watcher.unwatch(dir);
+        false
+    });
+
+    for dir in dirs {
+        if watched.contains(dir) {
+            continue;
+        }
+
+        match watcher.watch(Path::new(dir), RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                watched.insert(dir.clone());
+            }
+            Err(err) => {
+                event!(tracing::Level::WARN, "Failed to watch {:?}: {}", dir, err);
+            }
+        }
+    }
+}
+
+/// Wait for the next event, and collect all events that follow until the file system is quiet for
+/// `debounce`, or until the first event was held back for `MAX_LATENCY_FACTOR` times `debounce`.
+/// Returns `None` once the watcher is stopped.
+fn next_batch(receiver: &Receiver<Message>, debounce: Duration) -> Option<Vec<notify::Event>> {
+    let mut events = vec![];
+    let mut message = receiver.recv().ok()?;
+    let deadline = Instant::now() + debounce * MAX_LATENCY_FACTOR;
+
+    loop {
+        match message {
+            Message::Stop => return None,
+            Message::Event(Ok(event)) => events.push(event),
+            Message::Event(Err(err)) => {
+                event!(tracing::Level::WARN, "File watcher error: {}", err);
+            }
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Some(events);
+        }
+
+        message = match receiver.recv_timeout(debounce.min(remaining)) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => return Some(events),
+            Err(RecvTimeoutError::Disconnected) => return None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_batch, Message};
+    use notify::{Event, EventKind};
+    use std::sync::mpsc::channel;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_batches_are_delivered_while_events_keep_coming() {
+        let (sender, receiver) = channel();
+
+        // Keep sending events faster than the debounce window, until the receiver is dropped
+        std::thread::spawn(move || {
+            while sender
+                .send(Message::Event(Ok(Event::new(EventKind::Any))))
+                .is_ok()
+            {
+                std::thread::sleep(Duration::from_millis(2));
+            }
+        });
+
+        let started = Instant::now();
+        let events = next_batch(&receiver, Duration::from_millis(20)).unwrap();
+        assert!(!events.is_empty());
+        assert!(
+            started.elapsed() < Duration::from_secs(2),
+            "The batch was held back for {:?}",
+            started.elapsed()
+        );
+    }
+}
@@ -4,8 +4,9 @@ mod scanner {
     use pretty_assertions::assert_eq;
     use std::path::{Path, PathBuf};
     use std::process::Command;
+    use std::sync::mpsc::{Receiver, RecvTimeoutError};
     use std::thread::sleep;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
     use std::{fs, path};
 
     use tailwindcss_oxide::*;
@@ -688,10 +689,12 @@ mod scanner {
             ("index.html", ""),
             ("package-lock.json", ""),
             ("yarn.lock", ""),
+            (".env.local", ""),
         ]);
 
         assert_snapshot!(tree, @"
         . (git)
+        ├── ✗ .env.local
         ├── ✓ index.html
         ├── ✗ package-lock.json
         └── ✗ yarn.lock
@@ -1139,7 +1142,7 @@ mod scanner {
         let candidates = scanner
             .get_candidates_with_positions(ChangedContent::File(file, "html".into()))
             .into_iter()
-            .map(|(candidate, position)| (candidate.into_bytes(), position))
+            .map(|(candidate, position, _)| (candidate.into_bytes(), position))
             .collect::<Vec<_>>();
 
         assert_eq!(
@@ -1152,6 +1155,35 @@ mod scanner {
         );
     }
 
+    #[test]
+    fn it_should_return_the_content_with_the_positions() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("index.html");
+        let utf16 = [0xFF, 0xFE]
+            .into_iter()
+            .chain("é flex".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<_>>();
+        fs::write(&file, &utf16).unwrap();
+
+        let mut scanner = Scanner::new(vec![]);
+        let (content, positions) = scanner
+            .get_candidates_with_positions_and_content(ChangedContent::File(file, "html".into()));
+        assert_eq!(&*content, "é flex".as_bytes());
+        assert_eq!(positions.len(), 1);
+
+        let (candidate, position, _) = &positions[0];
+        assert_eq!(*position, 6);
+        assert_eq!(content.decoded_offset(*position), 3);
+        assert_eq!(candidate, "flex");
+
+        // A file that can't be read has no candidates
+        let (content, positions) = scanner.get_candidates_with_positions_and_content(
+            ChangedContent::File(dir.path().join("missing.html"), "html".into()),
+        );
+        assert!(content.is_empty());
+        assert!(positions.is_empty());
+    }
+
     #[test]
     fn it_should_preserve_valid_utf8_candidates() {
         let dir = tempdir().unwrap();
@@ -1336,6 +1368,62 @@ mod scanner {
         assert_eq!(normalized_sources, vec!["**/*", "foo.styl"]);
     }
 
+    #[test]
+    fn it_should_apply_the_ignore_rules_to_new_files_passed_to_scan_content() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(
+            &dir,
+            &[
+                (".gitignore", "ignored/\nignored.html"),
+                ("src/index.html", "flex"),
+                ("styles/index.html", "block"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![
+            public_source_entry_from_pattern(dir.join("src"), "@source '**/*'"),
+            public_source_entry_from_pattern(dir.join("styles"), "@source '**/*.css'"),
+        ]);
+        assert_eq!(scanner.scan(), vec!["flex"]);
+
+        create_files_in(
+            &dir,
+            &[
+                ("src/nested/new.html", "underline"),
+                ("src/ignored.html", "italic"),
+                ("src/ignored/index.html", "hidden"),
+                ("src/image.png", "grid"),
+                ("styles/new.html", "table"),
+                ("styles/new.css", "--color-red"),
+                ("outside/index.html", "contents"),
+            ],
+        );
+
+        let candidates = scanner.scan_content(
+            [
+                "src/nested/new.html",
+                "src/ignored.html",
+                "src/ignored/index.html",
+                "src/image.png",
+                "styles/new.html",
+                "styles/new.css",
+                "outside/index.html",
+            ]
+            .iter()
+            .map(|file| {
+                let file = dir.join(file);
+                let extension = file.extension().unwrap().to_string_lossy().to_string();
+                ChangedContent::File(file, extension)
+            })
+            .collect(),
+        );
+
+        assert_eq!(candidates, vec!["--color-red", "underline"]);
+    }
+
     #[test]
     fn it_should_pick_up_new_files() {
         // Create a temporary working directory
@@ -1494,77 +1582,986 @@ mod scanner {
     }
 
     #[test]
-    fn it_should_remove_deleted_files_from_scanned_files() {
-        // Create a temporary working directory
+    fn it_should_remove_deleted_files_from_scanned_files() {
+        // Create a temporary working directory
+        let dir = tempdir().unwrap().into_path();
+
+        // Initialize this directory as a git repository
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        // Create files
+        create_files_in(
+            &dir,
+            &[
+                ("src/index.html", "content-['src/index.html']"),
+                ("src/keep.html", "content-['src/keep.html']"),
+                ("src/remove.html", "content-['src/remove.html']"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        scanner.scan();
+        assert_eq!(
+            scanned_files(&mut scanner, &dir),
+            vec!["src/index.html", "src/keep.html", "src/remove.html"]
+        );
+
+        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
+        . (git)
+        └── ✓ src
+            ├── ✓ index.html
+            ├── ✓ keep.html
+            └── ✓ remove.html
+        ");
+
+        fs::remove_file(dir.join("src/remove.html")).unwrap();
+
+        scanner.scan();
+        assert_eq!(
+            scanned_files(&mut scanner, &dir),
+            vec!["src/index.html", "src/keep.html"]
+        );
+
+        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
+        . (git)
+        └── ✓ src
+            ├── ✓ index.html
+            └── ✓ keep.html
+        ");
+    }
+
+    #[test]
+    fn it_should_remove_deleted_directories_from_scanned_files() {
+        // Create a temporary working directory
+        let dir = tempdir().unwrap().into_path();
+
+        // Initialize this directory as a git repository
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        // Create files
+        create_files_in(
+            &dir,
+            &[
+                ("src/index.html", "content-['src/index.html']"),
+                ("src/keep/index.html", "content-['src/keep/index.html']"),
+                ("src/remove/index.html", "content-['src/remove/index.html']"),
+                (
+                    "src/remove/nested/index.html",
+                    "content-['src/remove/nested/index.html']",
+                ),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        scanner.scan();
+        assert_eq!(
+            scanned_files(&mut scanner, &dir),
+            vec![
+                "src/index.html",
+                "src/keep/index.html",
+                "src/remove/index.html",
+                "src/remove/nested/index.html",
+            ]
+        );
+
+        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
+        . (git)
+        └── ✓ src
+            ├── ✓ index.html
+            ├── ✓ keep
+            │   └── ✓ index.html
+            └── ✓ remove
+                ├── ✓ index.html
+                └── ✓ nested
+                    └── ✓ index.html
+        ");
+
+        fs::remove_dir_all(dir.join("src/remove")).unwrap();
+
+        scanner.scan();
+        assert_eq!(
+            scanned_files(&mut scanner, &dir),
+            vec!["src/index.html", "src/keep/index.html"]
+        );
+
+        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
+        . (git)
+        └── ✓ src
+            ├── ✓ index.html
+            └── ✓ keep
+                └── ✓ index.html
+        ");
+    }
+
+    #[test]
+    fn it_should_remove_deleted_directories_from_scanned_globs() {
+        // Create a temporary working directory
+        let dir = tempdir().unwrap().into_path();
+
+        // Initialize this directory as a git repository
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        // Create files
+        create_files_in(
+            &dir,
+            &[
+                ("index.html", "content-['index.html']"),
+                ("src/index.html", "content-['src/index.html']"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        scanner.scan();
+
+        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
+        . (git)
+        ├── ✓ index.html
+        └── ✓ src
+            └── ✓ index.html
+        ");
+
+        let globs = scanned_globs(&mut scanner, &dir);
+        assert!(globs.iter().any(|glob| glob.starts_with("src/**/*")));
+
+        fs::remove_dir_all(dir.join("src")).unwrap();
+
+        scanner.scan();
+
+        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
+        . (git)
+        └── ✓ index.html
+        ");
+
+        let globs = scanned_globs(&mut scanner, &dir);
+        assert!(!globs.iter().any(|glob| glob.starts_with("src/**/*")));
+    }
+
+    #[test]
+    fn it_should_track_files_scanned_by_the_last_scan() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(
+            &dir,
+            &[
+                ("src/index.html", "content-['src/index.html']"),
+                ("src/keep.html", "content-['src/keep.html']"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        assert_eq!(
+            scanner.scan(),
+            vec!["content-['src/index.html']", "content-['src/keep.html']"]
+        );
+
+        assert_eq!(
+            scanner.scan(),
+            vec!["content-['src/index.html']", "content-['src/keep.html']"]
+        );
+        assert_eq!(scanner.get_scanned_files(), Vec::<String>::new());
+
+        sleep(Duration::from_millis(10));
+        fs::write(dir.join("src/index.html"), "content-['src/changed.html']").unwrap();
+
+        assert_eq!(
+            scanner.scan(),
+            vec!["content-['src/changed.html']", "content-['src/keep.html']"]
+        );
+        assert_eq!(
+            normalize_files(scanner.get_scanned_files(), &dir),
+            vec!["src/index.html"]
+        );
+    }
+
+    #[test]
+    fn it_should_remove_candidates_of_changed_and_deleted_files() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(
+            &dir,
+            &[
+                ("src/index.html", "flex underline"),
+                ("src/other.html", "flex block"),
+                ("src/remove.html", "hidden"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        assert_eq!(scanner.scan(), vec!["block", "flex", "hidden", "underline"]);
+
+        // Deleted files no longer contribute candidates
+        fs::remove_file(dir.join("src/remove.html")).unwrap();
+        assert_eq!(scanner.scan(), vec!["block", "flex", "underline"]);
+        assert_eq!(scanner.get_removed_candidates(), vec!["hidden"]);
+
+        // Candidates that are still used by another file are kept
+        sleep(Duration::from_millis(10));
+        fs::write(dir.join("src/index.html"), "italic").unwrap();
+        assert_eq!(scanner.scan(), vec!["block", "flex", "italic"]);
+        assert_eq!(scanner.get_removed_candidates(), vec!["underline"]);
+
+        // Changes passed in through `scan_content` are tracked the same way
+        fs::write(dir.join("src/other.html"), "grid").unwrap();
+        let diff = scanner.scan_content_diff(vec![ChangedContent::File(
+            dir.join("src/other.html"),
+            "html".into(),
+        )]);
+        assert_eq!(diff.added, vec!["grid"]);
+        assert_eq!(diff.removed, vec!["block", "flex"]);
+        assert_eq!(scanner.get_removed_candidates(), diff.removed);
+
+        let diff = scanner.scan_diff();
+        assert_eq!(diff.removed, Vec::<String>::new());
+        assert_eq!(scanner.get_removed_candidates(), Vec::<String>::new());
+        assert_eq!(scanner.scan(), vec!["grid", "italic"]);
+    }
+
+    #[test]
+    fn it_should_report_the_difference_between_scans() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(
+            &dir,
+            &[
+                ("src/index.html", "flex underline"),
+                ("src/other.html", "flex block"),
+                ("src/remove.html", "hidden"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        let diff = scanner.scan_diff();
+        assert_eq!(diff.added, vec!["block", "flex", "hidden", "underline"]);
+        assert_eq!(diff.removed, Vec::<String>::new());
+        assert_eq!(
+            normalize_files(diff.changed_files, &dir),
+            vec!["src/index.html", "src/other.html", "src/remove.html"]
+        );
+
+        // Nothing changed
+        assert_eq!(scanner.scan_diff(), ScanDiff::default());
+
+        sleep(Duration::from_millis(10));
+        fs::remove_file(dir.join("src/remove.html")).unwrap();
+        fs::write(dir.join("src/index.html"), "italic").unwrap();
+
+        let diff = scanner.scan_diff();
+        assert_eq!(diff.added, vec!["italic"]);
+        assert_eq!(diff.removed, vec!["hidden", "underline"]);
+        assert_eq!(
+            normalize_files(diff.changed_files, &dir),
+            vec!["src/index.html"]
+        );
+        assert_eq!(
+            normalize_files(diff.deleted_files, &dir),
+            vec!["src/remove.html"]
+        );
+
+        fs::write(dir.join("src/other.html"), "grid").unwrap();
+
+        let diff = scanner.scan_content_diff(vec![
+            ChangedContent::File(dir.join("src/other.html"), "html".into()),
+            ChangedContent::Content("flex".into(), "html".into()),
+        ]);
+        assert_eq!(diff.added, vec!["grid"]);
+        assert_eq!(diff.removed, vec!["block"]);
+        assert_eq!(
+            normalize_files(diff.changed_files, &dir),
+            vec!["src/other.html"]
+        );
+        assert_eq!(diff.deleted_files, Vec::<String>::new());
+    }
+
+    #[test]
+    fn it_should_report_symlinked_files_by_their_canonical_path() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(&dir, &[("shared/a.html", "flex"), ("src/index.html", "")]);
+        let _ = symlink_file(dir.join("shared/a.html"), dir.join("src/linked.html"));
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.join("src"),
+            "@source '**/*'",
+        )]);
+
+        let diff = scanner.scan_diff();
+        assert_eq!(diff.added, vec!["flex"]);
+        assert_eq!(
+            normalize_files(diff.changed_files, &dir),
+            vec!["shared/a.html", "src/index.html"]
+        );
+
+        fs::write(dir.join("shared/a.html"), "underline").unwrap();
+        let diff = scanner.scan_content_diff(vec![ChangedContent::File(
+            dir.join("src/linked.html"),
+            "html".into(),
+        )]);
+        assert_eq!(diff.added, vec!["underline"]);
+        assert_eq!(
+            normalize_files(diff.changed_files, &dir),
+            vec!["shared/a.html"]
+        );
+
+        fs::remove_file(dir.join("src/linked.html")).unwrap();
+        let diff = scanner.scan_diff();
+        assert_eq!(diff.removed, vec!["underline"]);
+        assert_eq!(
+            normalize_files(diff.deleted_files, &dir),
+            vec!["shared/a.html"]
+        );
+    }
+
+    #[test]
+    fn it_should_detect_changes_by_content_hash() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(
+            &dir,
+            &[("src/index.html", "flex"), ("src/other.html", "block")],
+        );
+
+        let mut scanner = Scanner::new_with_options(
+            vec![public_source_entry_from_pattern(
+                dir.clone(),
+                "@source '**/*'",
+            )],
+            ScannerOptions {
+                change_detection: ChangeDetection::ContentHash,
+                ..Default::default()
+            },
+        );
+        assert_eq!(scanner.scan(), vec!["block", "flex"]);
+
+        // Touching a file without changing its contents is not a change
+        sleep(Duration::from_millis(10));
+        fs::write(dir.join("src/index.html"), "flex").unwrap();
+        assert_eq!(scanner.scan_diff(), ScanDiff::default());
+
+        // Changing a file while keeping its size and mtime is a change, because the mtime is too
+        // close to the last scan to be trusted.
+        let mtime = fs::metadata(dir.join("src/other.html"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        fs::write(dir.join("src/other.html"), "grid!").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(dir.join("src/other.html"))
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let diff = scanner.scan_diff();
+        assert_eq!(diff.added, vec!["grid!"]);
+        assert_eq!(diff.removed, vec!["block"]);
+        assert_eq!(
+            normalize_files(diff.changed_files, &dir),
+            vec!["src/other.html"]
+        );
+    }
+
+    #[test]
+    fn it_should_allow_changing_the_auto_source_detection_rules() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(
+            &dir,
+            &[
+                (".venv/index.html", "content-['.venv/index.html']"),
+                (
+                    "dist-storybook/index.html",
+                    "content-['dist-storybook/index.html']",
+                ),
+                ("src/index.html", "content-['src/index.html']"),
+                ("src/logo.png", "content-['src/logo.png']"),
+            ],
+        );
+
+        let mut scanner = Scanner::new_with_options(
+            vec![public_source_entry_from_pattern(
+                dir.clone(),
+                "@source '**/*'",
+            )],
+            ScannerOptions {
+                auto_source_detection: AutoSourceDetectionOptions {
+                    ignored_content_dirs: RuleListChanges {
+                        add: vec!["dist-storybook".into()],
+                        remove: vec![".venv".into()],
+                    },
+                    binary_extensions: RuleListChanges {
+                        add: vec![],
+                        remove: vec!["png".into()],
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            scanner.scan(),
+            vec![
+                "content-['.venv/index.html']",
+                "content-['src/index.html']",
+                "content-['src/logo.png']"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_use_the_configured_pre_processors() {
+        let dir = tempdir().unwrap().into_path();
+
+        create_files_in(
+            &dir,
+            &[
+                ("src/Card.view", "<div class:px-4={active}></div>"),
+                ("src/index.blade.php", "<div class:py-2={active}></div>"),
+                ("src/index.php", "<div class:my-2={active}></div>"),
+                ("src/emails/welcome.html", "<div class:mx-4={active}></div>"),
+            ],
+        );
+
+        let mut scanner = Scanner::new_with_options(
+            vec![public_source_entry_from_pattern(
+                dir.clone(),
+                "@source '**/*'",
+            )],
+            ScannerOptions {
+                pre_processors: vec![
+                    PreProcessorMapping {
+                        pattern: "view".into(),
+                        pre_processor: BuiltinPreProcessor::Svelte,
+                    },
+                    PreProcessorMapping {
+                        pattern: "blade.php".into(),
+                        pre_processor: BuiltinPreProcessor::Svelte,
+                    },
+                    PreProcessorMapping {
+                        pattern: "**/emails/*.html".into(),
+                        pre_processor: BuiltinPreProcessor::Svelte,
+                    },
+                ],
+                ..Default::default()
+            },
+        );
+
+        let candidates = scanner.scan();
+        assert!(candidates.contains(&"px-4".to_string()));
+        assert!(candidates.contains(&"py-2".to_string()));
+        assert!(candidates.contains(&"mx-4".to_string()));
+        assert!(!candidates.contains(&"my-2".to_string()));
+    }
+
+    #[test]
+    fn it_should_use_registered_pre_processors() {
+        /// Turns `{{ class "flex" }}` into `{{ class  flex  }}`
+        struct Quotes;
+
+        impl PreProcessor for Quotes {
+            fn process(&self, content: &[u8]) -> Vec<u8> {
+                content
+                    .iter()
+                    .map(|byte| if *byte == b'"' { b' ' } else { *byte })
+                    .collect()
+            }
+        }
+
+        let dir = tempdir().unwrap().into_path();
+
+        create_files_in(
+            &dir,
+            &[
+                ("src/index.tpl", r#"{{ class "flex" }}"#),
+                ("src/App.svelte", "<div class:underline={active}></div>"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+        scanner.register_pre_processor("tpl", Box::new(Quotes));
+        scanner.unregister_pre_processor("svelte");
+
+        let candidates = scanner.scan();
+        assert!(candidates.contains(&"flex".to_string()));
+        assert!(!candidates.contains(&"underline".to_string()));
+    }
+
+    #[test]
+    fn it_should_return_the_positions_of_all_candidates() {
+        let dir = tempdir().unwrap().into_path();
+
+        create_files_in(
+            &dir,
+            &[
+                (
+                    "src/a.html",
+                    "<div class=\"flex\">\n  <p class=\"underline flex\"></p>\n</div>",
+                ),
+                ("src/b.html", "<p class=\"group-[]:flex\"></p>"),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        let positions = scanner
+            .get_all_candidates_with_positions()
+            .into_iter()
+            .filter(|position| {
+                ["flex", "underline", "group-[]:flex"].contains(&position.candidate.as_str())
+            })
+            .map(|position| {
+                (
+                    position
+                        .file
+                        .strip_prefix(&dir)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                    position.candidate,
+                    position.range,
+                    position.line,
+                    position.column,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            positions,
+            vec![
+                ("src/a.html".to_string(), "flex".to_string(), 12..16, 1, 12),
+                (
+                    "src/a.html".to_string(),
+                    "underline".to_string(),
+                    31..40,
+                    2,
+                    12
+                ),
+                ("src/a.html".to_string(), "flex".to_string(), 41..45, 2, 22),
+                (
+                    "src/b.html".to_string(),
+                    "group-[]:flex".to_string(),
+                    10..23,
+                    1,
+                    10
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_return_the_positions_of_symlinked_files_once() {
+        let dir = tempdir().unwrap().into_path();
+
+        create_files_in(&dir, &[("shared/a.html", "<div class=\"flex\"></div>")]);
+        let _ = symlink_file(dir.join("shared/a.html"), dir.join("linked.html"));
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        let positions = scanner
+            .get_all_candidates_with_positions()
+            .into_iter()
+            .filter(|position| position.candidate == "flex")
+            .map(|position| {
+                (
+                    position
+                        .file
+                        .strip_prefix(&dir)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                    position.range,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Both the symlink and its target are walked, but it's the same file
+        assert_eq!(positions, vec![("shared/a.html".to_string(), 12..16)]);
+    }
+
+    #[test]
+    fn it_should_scan_large_files() {
+        let dir = tempdir().unwrap().into_path();
+
+        // Large enough to be memory mapped
+        let filler = "<p>lorem ipsum</p>\n".repeat(64 * 1024);
+        create_files_in(
+            &dir,
+            &[
+                (
+                    "src/index.html",
+                    &format!("{filler}<div class=\"flex\"></div>"),
+                ),
+                (
+                    "src/data.json",
+                    &format!("{filler}{{\"class\":\"underline\"}}"),
+                ),
+            ],
+        );
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        let candidates = scanner.scan();
+        assert!(candidates.contains(&"flex".to_string()));
+        assert!(candidates.contains(&"underline".to_string()));
+    }
+
+    #[test]
+    fn it_should_skip_large_and_minified_files() {
+        let dir = tempdir().unwrap().into_path();
+
+        create_files_in(
+            &dir,
+            &[
+                ("src/index.html", "flex"),
+                ("src/large.html", &"<p class=\"block\"></p>\n".repeat(1024)),
+                ("src/vendor.min.js", &"a=\"underline\";".repeat(1024)),
+            ],
+        );
+
+        let mut scanner = Scanner::new_with_options(
+            vec![public_source_entry_from_pattern(
+                dir.clone(),
+                "@source '**/*'",
+            )],
+            ScannerOptions {
+                max_file_size: Some(16 * 1024),
+                skip_minified_files: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(scanner.scan(), vec!["flex"]);
+
+        // Files that grow too large are forgotten
+        fs::write(dir.join("src/index.html"), "flex\n".repeat(4 * 1024)).unwrap();
+        let diff = scanner.scan_content_diff(vec![ChangedContent::File(
+            dir.join("src/index.html"),
+            "html".into(),
+        )]);
+        assert_eq!(diff.removed, vec!["flex"]);
+    }
+
+    #[test]
+    fn it_should_scan_utf16_files() {
+        let dir = tempdir().unwrap().into_path();
+        let content = "<div class=\"flex\">@Model.Name</div>\r\n<p class=\"underline\">é</p>";
+        let utf16 = [0xFF, 0xFE]
+            .into_iter()
+            .chain(content.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<_>>();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/index.cshtml"), &utf16).unwrap();
+        fs::write(dir.join("src/bom.html"), "\u{feff}block").unwrap();
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+        let candidates = scanner.scan();
+        assert!(candidates.contains(&"flex".to_string()));
+        assert!(candidates.contains(&"underline".to_string()));
+        assert!(candidates.contains(&"block".to_string()));
+
+        // Positions point to the original bytes
+        let positions = scanner.get_all_candidates_with_positions();
+        let underline = positions
+            .iter()
+            .find(|position| position.candidate == "underline")
+            .unwrap();
+        assert_eq!(
+            utf16[underline.range.clone()],
+            "underline"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(underline.line, 2);
+        assert_eq!(underline.column, 20);
+
+        let block = positions
+            .iter()
+            .find(|position| position.candidate == "block")
+            .unwrap();
+        assert_eq!(block.range, 3..8);
+
+        // Every file comes with the decoded content its positions point into
+        let candidates = scanner.map_all_candidates_with_positions(|content, positions| {
+            positions
+                .iter()
+                .map(|position| {
+                    let start = content.decoded_offset(position.range.start);
+                    let end = content.decoded_offset(position.range.end);
+                    String::from_utf8(content[start..end].to_vec()).unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            candidates,
+            vec![
+                vec!["block".to_string()],
+                vec!["class", "flex", "class", "underline"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect::<Vec<_>>(),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_not_read_unchanged_utf16_files_again() {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        let utf16 = [0xFF, 0xFE]
+            .into_iter()
+            .chain("flex underline".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<_>>();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/index.cshtml"), &utf16).unwrap();
+
+        // Make sure the mtime is old enough to be trusted
+        fs::File::options()
+            .write(true)
+            .open(dir.join("src/index.cshtml"))
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(60))
+            .unwrap();
+
+        let sources = vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )];
+        let options = ScannerOptions {
+            cache_dir: Some(dir.join(".tailwindcss/cache")),
+            change_detection: ChangeDetection::ContentHash,
+            ..Default::default()
+        };
+
+        let mut scanner = Scanner::new_with_options(sources.clone(), options.clone());
+        assert_eq!(scanner.scan(), vec!["flex", "underline"]);
+        assert_eq!(scanner.get_scan_stats().reading.files, 1);
+
+        // The size on disk matches the size of the last scan
+        assert_eq!(scanner.scan(), vec!["flex", "underline"]);
+        assert_eq!(scanner.get_scan_stats().reading.files, 0);
+
+        // And the size of the persistent cache
+        let mut scanner = Scanner::new_with_options(sources, options);
+        assert_eq!(scanner.scan(), vec!["flex", "underline"]);
+        assert_eq!(scanner.get_scan_stats().reading.files, 0);
+    }
+
+    #[test]
+    fn it_should_skip_binary_files() {
+        let dir = tempdir().unwrap().into_path();
+        create_files_in(&dir, &[("src/index.html", "flex")]);
+        fs::write(dir.join("src/app"), b"\x7fELF\x02\x01\x01\0\0\0 underline").unwrap();
+        fs::write(dir.join("src/data.db3"), b"SQLite format 3\0 block").unwrap();
+
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+        assert_eq!(scanner.scan(), vec!["flex"]);
+        assert_eq!(
+            scanner.get_scan_stats().binary_files,
+            vec![
+                dir.join("src/app").to_string_lossy().to_string(),
+                dir.join("src/data.db3").to_string_lossy().to_string(),
+            ]
+        );
+
+        // Binary files passed to `scan_content` are skipped as well
+        fs::write(dir.join("src/index.html"), b"\0grid").unwrap();
+        let diff = scanner.scan_content_diff(vec![ChangedContent::File(
+            dir.join("src/index.html"),
+            "html".into(),
+        )]);
+        assert_eq!(diff.removed, vec!["flex"]);
+        assert_eq!(
+            scanner.get_scan_stats().binary_files,
+            vec![dir.join("src/index.html").to_string_lossy().to_string()]
+        );
+    }
+
+    #[test]
+    fn it_should_report_candidate_usage() {
         let dir = tempdir().unwrap().into_path();
-
-        // Initialize this directory as a git repository
-        let _ = Command::new("git").arg("init").current_dir(&dir).output();
-
-        // Create files
         create_files_in(
             &dir,
             &[
-                ("src/index.html", "content-['src/index.html']"),
-                ("src/keep.html", "content-['src/keep.html']"),
-                ("src/remove.html", "content-['src/remove.html']"),
+                (
+                    "src/index.html",
+                    "<div class=\"flex\"></div> flex underline",
+                ),
+                ("src/about.html", "flex"),
+                ("src/app.jsx", "flex flex"),
             ],
         );
 
-        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+        let sources = vec![public_source_entry_from_pattern(
             dir.clone(),
             "@source '**/*'",
-        )]);
+        )];
 
+        // Usage statistics are opt-in
+        let mut scanner = Scanner::new(sources.clone());
         scanner.scan();
-        assert_eq!(
-            scanned_files(&mut scanner, &dir),
-            vec!["src/index.html", "src/keep.html", "src/remove.html"]
+        assert_eq!(scanner.get_usage_report(), None);
+
+        let mut scanner = Scanner::new_with_options(
+            sources,
+            ScannerOptions {
+                usage_statistics: true,
+                ..Default::default()
+            },
         );
+        scanner.scan();
 
-        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
-        . (git)
-        └── ✓ src
-            ├── ✓ index.html
-            ├── ✓ keep.html
-            └── ✓ remove.html
-        ");
+        let usage = |scanner: &Scanner, candidate: &str| {
+            let report = scanner.get_usage_report().unwrap();
+            report
+                .candidates
+                .into_iter()
+                .find(|usage| usage.candidate == candidate)
+                .map(|usage| (usage.occurrences, usage.files))
+        };
+        let extension = |scanner: &Scanner, extension: &str| {
+            let report = scanner.get_usage_report().unwrap();
+            report
+                .extensions
+                .into_iter()
+                .find(|usage| usage.extension == extension)
+                .map(|usage| (usage.files, usage.occurrences))
+        };
 
-        fs::remove_file(dir.join("src/remove.html")).unwrap();
+        assert_eq!(usage(&scanner, "flex"), Some((5, 3)));
+        assert_eq!(usage(&scanner, "underline"), Some((1, 1)));
+        assert_eq!(extension(&scanner, "jsx"), Some((1, 2)));
+
+        // Changed files replace their previous counts
+        fs::write(dir.join("src/app.jsx"), "underline").unwrap();
+        scanner.scan_content(vec![ChangedContent::File(
+            dir.join("src/app.jsx"),
+            "jsx".into(),
+        )]);
+        assert_eq!(usage(&scanner, "flex"), Some((3, 2)));
+        assert_eq!(usage(&scanner, "underline"), Some((2, 2)));
+
+        // Raw content counts as occurrences, but not as a file
+        scanner.scan_content(vec![ChangedContent::Content(
+            "flex flex".into(),
+            "html".into(),
+        )]);
+        assert_eq!(usage(&scanner, "flex"), Some((5, 2)));
+
+        // Removed files no longer count
+        fs::remove_file(dir.join("src/app.jsx")).unwrap();
+        scanner.scan_content(vec![ChangedContent::Removed(dir.join("src/app.jsx"))]);
+        assert_eq!(usage(&scanner, "underline"), Some((1, 1)));
+        assert_eq!(extension(&scanner, "jsx"), None);
+
+        let json = scanner.get_usage_report().unwrap().to_json();
+        assert!(json.contains(r#"{"candidate":"underline","occurrences":1,"files":1}"#));
+    }
+
+    #[test]
+    fn it_should_report_phase_stats() {
+        let dir = tempdir().unwrap().into_path();
+        let svelte = "<div class:underline={active}></div>";
+        create_files_in(
+            &dir,
+            &[("src/index.html", "flex"), ("src/App.svelte", svelte)],
+        );
 
+        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
         scanner.scan();
+
+        let stats = scanner.get_scan_stats();
+        let bytes = (4 + svelte.len()) as u64;
+        assert_eq!((stats.walking.files, stats.walking.bytes), (2, bytes));
+        assert_eq!((stats.reading.files, stats.reading.bytes), (2, bytes));
         assert_eq!(
-            scanned_files(&mut scanner, &dir),
-            vec!["src/index.html", "src/keep.html"]
+            (stats.pre_processing.files, stats.pre_processing.bytes),
+            (1, svelte.len() as u64)
         );
+        assert_eq!((stats.extraction.files, stats.extraction.bytes), (2, bytes));
+        assert!(stats.duration > Duration::ZERO);
 
-        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
-        . (git)
-        └── ✓ src
-            ├── ✓ index.html
-            └── ✓ keep.html
-        ");
+        // Raw content is only extracted
+        scanner.scan_content(vec![ChangedContent::Content("grid".into(), "html".into())]);
+
+        let stats = scanner.get_scan_stats();
+        assert_eq!(stats.walking, Default::default());
+        assert_eq!(stats.reading, Default::default());
+        assert_eq!((stats.extraction.files, stats.extraction.bytes), (1, 4));
     }
 
     #[test]
-    fn it_should_remove_deleted_directories_from_scanned_files() {
-        // Create a temporary working directory
+    fn it_should_forget_removed_and_renamed_files() {
         let dir = tempdir().unwrap().into_path();
 
-        // Initialize this directory as a git repository
         let _ = Command::new("git").arg("init").current_dir(&dir).output();
 
-        // Create files
         create_files_in(
             &dir,
             &[
-                ("src/index.html", "content-['src/index.html']"),
-                ("src/keep/index.html", "content-['src/keep/index.html']"),
-                ("src/remove/index.html", "content-['src/remove/index.html']"),
-                (
-                    "src/remove/nested/index.html",
-                    "content-['src/remove/nested/index.html']",
-                ),
+                ("src/index.html", "flex"),
+                ("src/remove.html", "hidden"),
+                ("src/rename.html", "underline"),
+                ("src/nested/a.html", "block"),
+                ("src/nested/b.html", "grid"),
             ],
         );
 
@@ -1572,96 +2569,162 @@ mod scanner {
             dir.clone(),
             "@source '**/*'",
         )]);
-
         scanner.scan();
+
+        fs::remove_file(dir.join("src/remove.html")).unwrap();
+        fs::rename(dir.join("src/rename.html"), dir.join("src/renamed.html")).unwrap();
+        fs::remove_dir_all(dir.join("src/nested")).unwrap();
+
+        let diff = scanner.scan_content_diff(vec![
+            ChangedContent::Removed(dir.join("src/remove.html")),
+            ChangedContent::Renamed(
+                dir.join("src/rename.html"),
+                dir.join("src/renamed.html"),
+                "html".into(),
+            ),
+            ChangedContent::Removed(dir.join("src/nested")),
+        ]);
+        assert_eq!(diff.added, Vec::<String>::new());
+        assert_eq!(diff.removed, vec!["block", "grid", "hidden"]);
         assert_eq!(
-            scanned_files(&mut scanner, &dir),
-            vec![
-                "src/index.html",
-                "src/keep/index.html",
-                "src/remove/index.html",
-                "src/remove/nested/index.html",
-            ]
+            normalize_files(diff.changed_files, &dir),
+            vec!["src/renamed.html"]
         );
-
-        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
-        . (git)
-        └── ✓ src
-            ├── ✓ index.html
-            ├── ✓ keep
-            │   └── ✓ index.html
-            └── ✓ remove
-                ├── ✓ index.html
-                └── ✓ nested
-                    └── ✓ index.html
-        ");
-
-        fs::remove_dir_all(dir.join("src/remove")).unwrap();
-
-        scanner.scan();
         assert_eq!(
-            scanned_files(&mut scanner, &dir),
-            vec!["src/index.html", "src/keep/index.html"]
+            normalize_files(diff.deleted_files, &dir),
+            vec![
+                "src/nested/a.html",
+                "src/nested/b.html",
+                "src/remove.html",
+                "src/rename.html"
+            ]
         );
 
-        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
-        . (git)
-        └── ✓ src
-            ├── ✓ index.html
-            └── ✓ keep
-                └── ✓ index.html
-        ");
+        // A full scan agrees with the state after the removals
+        let diff = scanner.scan_diff();
+        assert_eq!(diff.added, Vec::<String>::new());
+        assert_eq!(diff.removed, Vec::<String>::new());
+        assert_eq!(diff.deleted_files, Vec::<String>::new());
     }
 
     #[test]
-    fn it_should_remove_deleted_directories_from_scanned_globs() {
-        // Create a temporary working directory
+    fn it_should_reuse_candidates_from_the_persistent_cache() {
         let dir = tempdir().unwrap().into_path();
 
-        // Initialize this directory as a git repository
         let _ = Command::new("git").arg("init").current_dir(&dir).output();
 
-        // Create files
         create_files_in(
             &dir,
-            &[
-                ("index.html", "content-['index.html']"),
-                ("src/index.html", "content-['src/index.html']"),
-            ],
+            &[("src/index.html", "flex"), ("src/other.html", "underline")],
         );
 
-        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
+        let sources = vec![public_source_entry_from_pattern(
             dir.clone(),
             "@source '**/*'",
-        )]);
+        )];
+        let options = ScannerOptions {
+            cache_dir: Some(dir.join(".tailwindcss/cache")),
+            ..Default::default()
+        };
 
-        scanner.scan();
+        let mut scanner = Scanner::new_with_options(sources.clone(), options.clone());
+        assert_eq!(scanner.scan(), vec!["flex", "underline"]);
 
-        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
-        . (git)
-        ├── ✓ index.html
-        └── ✓ src
-            └── ✓ index.html
-        ");
+        // The cache is written, and is ignored by the scanner itself
+        let cache_file = fs::read_dir(dir.join(".tailwindcss/cache"))
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .unwrap();
+        assert_eq!(scanner.scan(), vec!["flex", "underline"]);
 
-        let globs = scanned_globs(&mut scanner, &dir);
-        assert!(globs.iter().any(|glob| glob.starts_with("src/**/*")));
+        // Tamper with the cache, so we can tell whether a new scanner reads it
+        let contents = fs::read_to_string(&cache_file).unwrap();
+        fs::write(&cache_file, contents.replace("\"flex\"", "\"cached\"")).unwrap();
 
-        fs::remove_dir_all(dir.join("src")).unwrap();
+        let mut scanner = Scanner::new_with_options(sources.clone(), options.clone());
+        assert_eq!(scanner.scan(), vec!["cached", "underline"]);
+
+        // Touching a file without changing its contents keeps using the cache
+        sleep(Duration::from_millis(10));
+        fs::write(dir.join("src/index.html"), "flex").unwrap();
+
+        let mut scanner = Scanner::new_with_options(sources.clone(), options.clone());
+        assert_eq!(scanner.scan(), vec!["cached", "underline"]);
+
+        // Changing the contents invalidates the cached entry
+        fs::write(dir.join("src/index.html"), "block").unwrap();
+
+        let mut scanner = Scanner::new_with_options(sources.clone(), options.clone());
+        assert_eq!(scanner.scan(), vec!["block", "underline"]);
+
+        let mut scanner = Scanner::new_with_options(sources.clone(), options.clone());
+        assert_eq!(scanner.scan(), vec!["block", "underline"]);
 
+        // Options that change which candidates are extracted invalidate the cache
+        let contents = fs::read_to_string(&cache_file).unwrap();
+        fs::write(&cache_file, contents.replace("\"block\"", "\"cached\"")).unwrap();
+
+        let mut scanner = Scanner::new_with_options(
+            sources.clone(),
+            ScannerOptions {
+                max_file_size: Some(1024),
+                ..options.clone()
+            },
+        );
+        assert_eq!(scanner.scan(), vec!["block", "underline"]);
+
+        // Files that changed through `scan_content` are cached with their new contents
+        fs::write(dir.join("src/index.html"), "grid").unwrap();
+        scanner.scan_content(vec![ChangedContent::File(
+            dir.join("src/index.html"),
+            "html".into(),
+        )]);
+        fs::write(dir.join("src/other.html"), "hidden").unwrap();
         scanner.scan();
 
-        assert_snapshot!(fs_tree(&dir, &scanned_files(&mut scanner, &dir)), @"
-        . (git)
-        └── ✓ index.html
-        ");
+        let mut scanner = Scanner::new_with_options(
+            sources,
+            ScannerOptions {
+                max_file_size: Some(1024),
+                ..options
+            },
+        );
+        assert_eq!(scanner.scan(), vec!["grid", "hidden"]);
+    }
 
-        let globs = scanned_globs(&mut scanner, &dir);
-        assert!(!globs.iter().any(|glob| glob.starts_with("src/**/*")));
+    #[test]
+    fn it_should_track_stamps_of_content_changes() {
+        let dir = tempdir().unwrap().into_path();
+        create_files_in(&dir, &[("src/index.html", "flex")]);
+
+        let mut scanner = Scanner::new_with_options(
+            vec![public_source_entry_from_pattern(
+                dir.clone(),
+                "@source '**/*'",
+            )],
+            ScannerOptions {
+                change_detection: ChangeDetection::ContentHash,
+                ..Default::default()
+            },
+        );
+        scanner.scan();
+
+        fs::write(dir.join("src/index.html"), "underline").unwrap();
+        let diff = scanner.scan_content_diff(vec![ChangedContent::File(
+            dir.join("src/index.html"),
+            "html".into(),
+        )]);
+        assert_eq!(diff.added, vec!["underline"]);
+
+        // The new contents are already known, so the next scan doesn't report the file again
+        let diff = scanner.scan_diff();
+        assert!(diff.is_empty(), "{diff:?}");
     }
 
     #[test]
-    fn it_should_track_files_scanned_by_the_last_scan() {
+    fn it_should_track_where_candidates_were_found() {
         let dir = tempdir().unwrap().into_path();
 
         let _ = Command::new("git").arg("init").current_dir(&dir).output();
@@ -1669,42 +2732,62 @@ mod scanner {
         create_files_in(
             &dir,
             &[
-                ("src/index.html", "content-['src/index.html']"),
-                ("src/keep.html", "content-['src/keep.html']"),
+                ("src/index.html", "<div class=\"flex underline\"></div>"),
+                ("src/other.html", "flex\n<p class=\"flex\">"),
+                ("src/styles.css", ".a { color: var(--my-color); }"),
             ],
         );
 
-        let mut scanner = Scanner::new(vec![public_source_entry_from_pattern(
-            dir.clone(),
-            "@source '**/*'",
-        )]);
-
-        assert_eq!(
-            scanner.scan(),
-            vec!["content-['src/index.html']", "content-['src/keep.html']"]
-        );
-
-        assert_eq!(
-            scanner.scan(),
-            vec!["content-['src/index.html']", "content-['src/keep.html']"]
-        );
-        assert_eq!(scanner.get_scanned_files(), Vec::<String>::new());
+        let mut scanner = Scanner::new(vec![
+            public_source_entry_from_pattern(dir.clone(), "@source '**/*'"),
+            public_source_entry_from_pattern(dir.clone(), "@source '**/*.css'"),
+        ]);
+        scanner.scan();
 
-        sleep(Duration::from_millis(10));
-        fs::write(dir.join("src/index.html"), "content-['src/changed.html']").unwrap();
+        let locations = scanner
+            .get_candidate_locations()
+            .into_iter()
+            .filter(|(candidate, _)| {
+                ["flex", "underline", "--my-color"].contains(&candidate.as_str())
+            })
+            .map(|(candidate, locations)| {
+                let locations = locations
+                    .into_iter()
+                    .map(|location| {
+                        (
+                            normalize_files(
+                                vec![location.file.to_string_lossy().to_string()],
+                                &dir,
+                            )
+                            .remove(0),
+                            location.offsets,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                (candidate, locations)
+            })
+            .collect::<Vec<_>>();
 
         assert_eq!(
-            scanner.scan(),
+            locations,
             vec![
-                "content-['src/changed.html']",
-                "content-['src/index.html']",
-                "content-['src/keep.html']",
+                (
+                    "--my-color".to_string(),
+                    vec![("src/styles.css".to_string(), vec![16])]
+                ),
+                (
+                    "flex".to_string(),
+                    vec![
+                        ("src/index.html".to_string(), vec![12]),
+                        ("src/other.html".to_string(), vec![0, 15]),
+                    ]
+                ),
+                (
+                    "underline".to_string(),
+                    vec![("src/index.html".to_string(), vec![17])]
+                ),
             ]
         );
-        assert_eq!(
-            normalize_files(scanner.get_scanned_files(), &dir),
-            vec!["src/index.html"]
-        );
     }
 
     #[test]
@@ -4077,4 +5160,126 @@ mod scanner {
 
         assert_eq!(candidates, vec!["--color-red", "--color-used-at-start"]);
     }
+
+    /// Applies the `change`, and waits until a diff passes the `is_expected` check. Returns all
+    /// diffs that were reported in the meantime.
+    ///
+    /// Changes made in quick succession can end up with the same mtime, and polling compares mtimes
+    /// in whole seconds. Instead of waiting for a fixed amount of time, the change is applied again
+    /// until it is noticed.
+    fn wait_for_diffs(
+        receiver: &Receiver<ScanDiff>,
+        change: impl Fn(),
+        is_expected: impl Fn(&ScanDiff) -> bool,
+    ) -> Vec<ScanDiff> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut diffs = vec![];
+
+        change();
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(250)) {
+                Ok(diff) => {
+                    let done = is_expected(&diff);
+                    diffs.push(diff);
+                    if done {
+                        return diffs;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) if Instant::now() < deadline => change(),
+                Err(err) => panic!("Expected change was not reported: {err}, got {diffs:?}"),
+            }
+        }
+    }
+
+    fn assert_watcher_reports_changes(options: WatchOptions) {
+        let dir = tempdir().unwrap().into_path();
+
+        let _ = Command::new("git").arg("init").current_dir(&dir).output();
+
+        create_files_in(
+            &dir,
+            &[
+                (".gitignore", "ignored/"),
+                ("src/index.html", "flex"),
+                ("ignored/index.html", "hidden"),
+            ],
+        );
+
+        let scanner = Scanner::new(vec![public_source_entry_from_pattern(
+            dir.clone(),
+            "@source '**/*'",
+        )]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let watcher = scanner
+            .watch(options, move |diff| sender.send(diff).unwrap())
+            .unwrap();
+
+        // The initial scan
+        let diff = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(diff.added, vec!["flex"]);
+
+        // Ignored files are not watched
+        fs::write(dir.join("ignored/index.html"), "italic").unwrap();
+
+        // Changing a file
+        let diffs = wait_for_diffs(
+            &receiver,
+            || fs::write(dir.join("src/index.html"), "underline").unwrap(),
+            |diff| !diff.is_empty(),
+        );
+        let [diff] = &diffs[..] else {
+            panic!("Expected a single diff, got {diffs:?}");
+        };
+        assert_eq!(diff.added, vec!["underline"]);
+        assert_eq!(diff.removed, vec!["flex"]);
+        assert_eq!(
+            normalize_files(diff.changed_files.clone(), &dir),
+            vec!["src/index.html"]
+        );
+
+        // Adding a file in a new folder
+        let diffs = wait_for_diffs(
+            &receiver,
+            || create_files_in(&dir, &[("src/nested/new.html", "grid")]),
+            |diff| diff.added.contains(&"grid".to_string()),
+        );
+        let added = diffs
+            .into_iter()
+            .flat_map(|diff| diff.added)
+            .collect::<Vec<_>>();
+        assert_eq!(added, vec!["grid"]);
+
+        // Deleting a file
+        let diffs = wait_for_diffs(
+            &receiver,
+            || _ = fs::remove_file(dir.join("src/index.html")),
+            |diff| !diff.is_empty(),
+        );
+        let [diff] = &diffs[..] else {
+            panic!("Expected a single diff, got {diffs:?}");
+        };
+        assert_eq!(diff.removed, vec!["underline"]);
+        assert_eq!(
+            normalize_files(diff.deleted_files.clone(), &dir),
+            vec!["src/index.html"]
+        );
+
+        let scanner = watcher.stop();
+        assert!(scanner.is_some());
+    }
+
+    #[test]
+    fn it_should_watch_sources_for_changes() {
+        assert_watcher_reports_changes(WatchOptions::default());
+    }
+
+    #[test]
+    fn it_should_watch_sources_for_changes_by_polling() {
+        assert_watcher_reports_changes(WatchOptions {
+            poll: true,
+            poll_interval: Duration::from_millis(50),
+            ..Default::default()
+        });
+    }
 }
@@ -10,7 +10,8 @@ export async function extractRawCandidates(
   let result = scanner.getCandidatesWithPositions({ content, extension })
 
   let candidates: { rawCandidate: string; start: number; end: number }[] = []
-  for (let { candidate: rawCandidate, position: start } of result) {
+  for (let { candidate: rawCandidate, position: start, kind } of result) {
+    if (kind !== 'candidate') continue
     candidates.push({ rawCandidate, start, end: start + rawCandidate.length })
   }
   return candidates